@@ -1,24 +1,21 @@
 #import bevy_pbr::mesh_view_bind_group
-#import bevy_pbr::mesh_struct
 
+// every plank and hole is an instance of one quad, with its cells in a shared atlas
 struct WoodMaterial {
     primary_color: vec4<f32>;
     secondary_color: vec4<f32>;
     hilight_color: vec4<f32>;
     texture_offset: vec2<i32>;
     size: vec2<u32>;
+    atlas_offset: vec2<u32>;
     turns: u32;
     is_plank: u32;
 };
 [[group(1), binding(0)]]
-var<uniform> material: WoodMaterial;
-[[group(1), binding(1)]]
-var base_color_texture: texture_2d<u32>;
-[[group(1), binding(2)]]
-var base_color_sampler: sampler;
+var atlas: texture_2d<u32>;
 
-[[group(2), binding(0)]]
-var<uniform> mesh: Mesh;
+// the instance being drawn, filled in at the start of the fragment shader
+var<private> material: WoodMaterial;
 
 // based on https://www.shadertoy.com/view/XssXRB
 fn rand(n: f32, seed: f32) -> f32 {
@@ -69,47 +66,87 @@ fn is_hole(xy: vec2<i32>) -> bool {
         return is_plank;
     }
 
-    var in_set: bool = textureLoad(base_color_texture, xy, 0).r != 0u;
+    var in_set: bool = textureLoad(atlas, vec2<i32>(material.atlas_offset) + xy, 0).r != 0u;
 
     return in_set != is_plank;
 }
 
-struct Vertex {
-    [[location(0)]] position: vec3<f32>;
-    [[location(1)]] normal: vec3<f32>;
-    [[location(2)]] uv: vec2<f32>;
+struct Instance {
+    [[builtin(vertex_index)]] index: u32;
+    [[location(0)]] model_0: vec4<f32>;
+    [[location(1)]] model_1: vec4<f32>;
+    [[location(2)]] model_2: vec4<f32>;
+    [[location(3)]] model_3: vec4<f32>;
+    [[location(4)]] primary_color: vec4<f32>;
+    [[location(5)]] secondary_color: vec4<f32>;
+    [[location(6)]] hilight_color: vec4<f32>;
+    [[location(7)]] texture_offset: vec2<i32>;
+    [[location(8)]] size: vec2<u32>;
+    [[location(9)]] atlas_offset: vec2<u32>;
+    [[location(10)]] turns_is_plank: vec2<u32>;
 };
 
 struct VertexOutput {
     [[builtin(position)]] clip_position: vec4<f32>;
-    [[location(0)]] world_position: vec4<f32>;
-    [[location(1)]] world_normal: vec3<f32>;
-    [[location(2)]] uv: vec2<f32>;
+    [[location(0)]] uv: vec2<f32>;
+    [[location(1)]] primary_color: vec4<f32>;
+    [[location(2)]] secondary_color: vec4<f32>;
+    [[location(3)]] hilight_color: vec4<f32>;
+    [[location(4), interpolate(flat)]] texture_offset: vec2<i32>;
+    [[location(5), interpolate(flat)]] size: vec2<u32>;
+    [[location(6), interpolate(flat)]] atlas_offset: vec2<u32>;
+    [[location(7), interpolate(flat)]] turns_is_plank: vec2<u32>;
 };
 
 struct FragmentInput {
     [[builtin(front_facing)]] is_front: bool;
-    [[location(0)]] world_position: vec4<f32>;
-    [[location(1)]] world_normal: vec3<f32>;
-    [[location(2)]] uv: vec2<f32>;
+    [[location(0)]] uv: vec2<f32>;
+    [[location(1)]] primary_color: vec4<f32>;
+    [[location(2)]] secondary_color: vec4<f32>;
+    [[location(3)]] hilight_color: vec4<f32>;
+    [[location(4), interpolate(flat)]] texture_offset: vec2<i32>;
+    [[location(5), interpolate(flat)]] size: vec2<u32>;
+    [[location(6), interpolate(flat)]] atlas_offset: vec2<u32>;
+    [[location(7), interpolate(flat)]] turns_is_plank: vec2<u32>;
 };
 
 [[stage(vertex)]]
-fn vertex(vertex: Vertex) -> VertexOutput {
+fn vertex(instance: Instance) -> VertexOutput {
+    // two triangles from the bottom left, 0-1-2 and 0-2-5
+    let corner = vec2<f32>(
+        f32((0x16u >> instance.index) & 1u),
+        f32((0x34u >> instance.index) & 1u)
+    );
+    // uvs count cells, like the quad's size
+    let uv = corner * vec2<f32>(instance.size);
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+
     var out: VertexOutput;
-    out.world_position = mesh.model * vec4<f32>(vertex.position, 1.0);
-    out.world_normal = mat3x3<f32>(
-        mesh.inverse_transpose_model[0].xyz,
-        mesh.inverse_transpose_model[1].xyz,
-        mesh.inverse_transpose_model[2].xyz
-    ) * vertex.normal;
-    out.uv = vertex.uv;
-    out.clip_position = view.view_proj * out.world_position;
+    out.clip_position = view.view_proj * model * vec4<f32>(uv, 0.0, 1.0);
+    out.uv = uv;
+    out.primary_color = instance.primary_color;
+    out.secondary_color = instance.secondary_color;
+    out.hilight_color = instance.hilight_color;
+    out.texture_offset = instance.texture_offset;
+    out.size = instance.size;
+    out.atlas_offset = instance.atlas_offset;
+    out.turns_is_plank = instance.turns_is_plank;
     return out;
 }
 
 [[stage(fragment)]]
 fn fragment(in: FragmentInput) -> [[location(0)]] vec4<f32> {
+    material = WoodMaterial(
+        in.primary_color,
+        in.secondary_color,
+        in.hilight_color,
+        in.texture_offset,
+        in.size,
+        in.atlas_offset,
+        in.turns_is_plank.x,
+        in.turns_is_plank.y
+    );
+
     var tile_uv = in.uv;
     var texture_uv = in.uv - 0.5;
     var right = vec2<i32>(1, 0);
@@ -0,0 +1,90 @@
+// run with `cargo bench`, needs nightly for the bench harness like the crate's own features
+#![feature(test)]
+
+extern crate test;
+
+use bevy::{
+    prelude::*,
+    render::texture::{CompressedImageFormats, ImageType},
+};
+use bevy_carp::{
+    cut::Cut,
+    levels::{build_level, spawn_random, LevelDef},
+    model::Plank,
+};
+use test::{black_box, Bencher};
+
+// the main menu's plank, the biggest the game makes
+fn title_plank() -> Plank {
+    let image = Image::from_buffer(
+        include_bytes!("../assets/images/title.png"),
+        ImageType::Extension("png"),
+        CompressedImageFormats::NONE,
+        true,
+    )
+    .unwrap();
+    Plank::from_pixels(&image.data, image.size().x as usize)
+}
+
+// as big as random sets get
+fn large_def() -> LevelDef {
+    LevelDef {
+        num_holes: 14,
+        total_blocks: 112,
+        seed: 1,
+        file: None,
+    }
+}
+
+fn large_plank() -> Plank {
+    build_level(&large_def()).planks.remove(0).0
+}
+
+// a single chop between the lowest, leftmost pair of cells, as after the first move of a cut. `is_finished`
+// runs `split` after every chop, and until the cut is through it floods the whole plank
+fn first_chop(plank: &Plank) -> Cut {
+    let ((left, right), (bottom, top)) = plank.extents();
+    let mut cut = Cut::default();
+    let pair = (bottom..=top)
+        .flat_map(|y| (left..right).map(move |x| (IVec2::new(x, y), IVec2::new(x + 1, y))))
+        .find(|(a, b)| plank.contains(*a) && plank.contains(*b))
+        .unwrap();
+    cut.separated.insert(pair);
+    cut
+}
+
+#[bench]
+fn generate_easy_set(b: &mut Bencher) {
+    b.iter(|| spawn_random(90, 0, 30, "Easy Set".into(), 11, 0, "bench"));
+}
+
+#[bench]
+fn build_large_level(b: &mut Bencher) {
+    let def = large_def();
+    b.iter(|| build_level(black_box(&def)));
+}
+
+#[bench]
+fn split_title_plank(b: &mut Bencher) {
+    let plank = title_plank();
+    let cut = first_chop(&plank);
+    b.iter(|| cut.split(black_box(&plank)));
+}
+
+#[bench]
+fn split_large_plank(b: &mut Bencher) {
+    let plank = large_plank();
+    let cut = first_chop(&plank);
+    b.iter(|| cut.split(black_box(&plank)));
+}
+
+#[bench]
+fn turn_large_plank(b: &mut Bencher) {
+    let plank = large_plank();
+    b.iter(|| {
+        let mut plank = plank.clone();
+        plank.rotate();
+        plank.shift(IVec2::ONE);
+        plank.normalize()
+    });
+}
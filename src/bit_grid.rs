@@ -0,0 +1,522 @@
+// a set of cells kept as one bit per cell over a bounding rectangle. moving the set only moves the rectangle,
+// and overlap and neighbour tests compare 64 cells at a time. the methods follow the HashSet it replaced.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const BITS: i32 = u64::BITS as i32;
+
+#[derive(Clone, Default)]
+pub struct BitGrid {
+    // the cell of the first bit
+    origin: IVec2,
+    width: i32,
+    height: i32,
+    // words per row
+    stride: usize,
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitGrid {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn with_bounds(min: IVec2, max: IVec2) -> Self {
+        let size = (max - min + 1).max(IVec2::ZERO);
+        let stride = (size.x as usize).div_ceil(BITS as usize);
+        Self {
+            origin: min,
+            width: size.x,
+            height: size.y,
+            stride,
+            words: vec![0; stride * size.y as usize],
+            len: 0,
+        }
+    }
+
+    /// an empty set with room for the cells of `other` without growing
+    pub fn empty_like(other: &BitGrid) -> Self {
+        Self::with_bounds(
+            other.origin,
+            other.origin + IVec2::new(other.width, other.height) - 1,
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn bit(&self, cell: IVec2) -> Option<(usize, u64)> {
+        let local = cell - self.origin;
+        if local.x < 0 || local.y < 0 || local.x >= self.width || local.y >= self.height {
+            return None;
+        }
+        let word = local.y as usize * self.stride + (local.x / BITS) as usize;
+        Some((word, 1 << (local.x % BITS)))
+    }
+
+    pub fn contains(&self, cell: &IVec2) -> bool {
+        self.bit(*cell)
+            .is_some_and(|(word, bit)| self.words[word] & bit != 0)
+    }
+
+    /// add a cell, returning false if it was already there
+    pub fn insert(&mut self, cell: IVec2) -> bool {
+        let (word, bit) = match self.bit(cell) {
+            Some(bit) => bit,
+            None => {
+                self.grow(cell);
+                self.bit(cell).unwrap()
+            }
+        };
+        if self.words[word] & bit != 0 {
+            return false;
+        }
+        self.words[word] |= bit;
+        self.len += 1;
+        true
+    }
+
+    /// remove a cell, returning false if it wasn't there
+    pub fn remove(&mut self, cell: &IVec2) -> bool {
+        let Some((word, bit)) = self.bit(*cell) else {
+            return false;
+        };
+        if self.words[word] & bit == 0 {
+            return false;
+        }
+        self.words[word] &= !bit;
+        self.len -= 1;
+        true
+    }
+
+    // make room for `cell`, with half as much again on the side that grew so cells added one at a time
+    // don't copy the grid every time
+    fn grow(&mut self, cell: IVec2) {
+        if self.words.is_empty() {
+            *self = Self::with_bounds(cell, cell);
+            return;
+        }
+        let size = IVec2::new(self.width, self.height);
+        let max = self.origin + size - 1;
+        let slack = size / 2;
+        let mut new_min = self.origin;
+        let mut new_max = max;
+        if cell.x < new_min.x {
+            new_min.x = cell.x - slack.x;
+        }
+        if cell.y < new_min.y {
+            new_min.y = cell.y - slack.y;
+        }
+        if cell.x > new_max.x {
+            new_max.x = cell.x + slack.x;
+        }
+        if cell.y > new_max.y {
+            new_max.y = cell.y + slack.y;
+        }
+
+        let mut grown = Self::with_bounds(new_min, new_max);
+        for cell in self.iter() {
+            grown.insert(cell);
+        }
+        *self = grown;
+    }
+
+    /// the cells in rows from the bottom, left to right
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            grid: self,
+            word: 0,
+            bits: self.words.first().copied().unwrap_or_default(),
+        }
+    }
+
+    pub fn intersection<'a>(&'a self, other: &'a BitGrid) -> impl Iterator<Item = IVec2> + 'a {
+        self.iter().filter(|cell| other.contains(cell))
+    }
+
+    pub fn difference<'a>(&'a self, other: &'a BitGrid) -> impl Iterator<Item = IVec2> + 'a {
+        self.iter().filter(|cell| !other.contains(cell))
+    }
+
+    /// move every cell by `by`
+    pub fn shift(&mut self, by: IVec2) {
+        self.origin += by;
+    }
+
+    /// the cells turned a quarter anticlockwise about (0, 0)
+    pub fn rotated(&self) -> Self {
+        let max = self.origin + IVec2::new(self.width, self.height) - 1;
+        let mut rotated = Self::with_bounds(
+            IVec2::new(-max.y, self.origin.x),
+            IVec2::new(-self.origin.y, max.x),
+        );
+        for cell in self.iter() {
+            rotated.insert(IVec2::new(-cell.y, cell.x));
+        }
+        rotated
+    }
+
+    /// the smallest and largest coordinates of the cells, `None` if empty
+    pub fn bounds(&self) -> Option<(IVec2, IVec2)> {
+        let mut bounds: Option<(IVec2, IVec2)> = None;
+        for (i, &word) in self.words.iter().enumerate() {
+            if word == 0 {
+                continue;
+            }
+            let row = (i / self.stride) as i32;
+            let column = (i % self.stride) as i32 * BITS;
+            let low = self.origin + IVec2::new(column + word.trailing_zeros() as i32, row);
+            let high =
+                self.origin + IVec2::new(column + BITS - 1 - word.leading_zeros() as i32, row);
+            bounds = Some(match bounds {
+                Some((min, max)) => (min.min(low), max.max(high)),
+                None => (low, high),
+            });
+        }
+        bounds
+    }
+
+    // the 64 cells of row `y` from column `x`, clear outside the grid
+    fn row_bits(&self, y: i32, x: i32) -> u64 {
+        let row = y - self.origin.y;
+        if row < 0 || row >= self.height {
+            return 0;
+        }
+        let column = x - self.origin.x;
+        let word = |i: i32| match i >= 0 && (i as usize) < self.stride {
+            true => self.words[row as usize * self.stride + i as usize],
+            false => 0,
+        };
+        let (i, offset) = (column.div_euclid(BITS), column.rem_euclid(BITS));
+        match offset {
+            0 => word(i),
+            _ => word(i) >> offset | word(i + 1) << (BITS - offset),
+        }
+    }
+
+    // each row of this grid's rectangle as (y, x) for every 64 columns
+    fn chunks(&self) -> impl Iterator<Item = (i32, i32)> {
+        let (origin, width) = (self.origin, self.width);
+        (origin.y..origin.y + self.height).flat_map(move |y| {
+            (origin.x..origin.x + width)
+                .step_by(BITS as usize)
+                .map(move |x| (y, x))
+        })
+    }
+
+    /// any cell in both sets
+    pub fn overlaps(&self, other: &BitGrid) -> bool {
+        self.chunks()
+            .any(|(y, x)| self.row_bits(y, x) & other.row_bits(y, x) != 0)
+    }
+
+    /// any cell of `other` next to a cell of this set
+    pub fn touches(&self, other: &BitGrid) -> bool {
+        other.chunks().any(|(y, x)| {
+            let next_to = self.row_bits(y, x - 1)
+                | self.row_bits(y, x + 1)
+                | self.row_bits(y - 1, x)
+                | self.row_bits(y + 1, x);
+            other.row_bits(y, x) & next_to != 0
+        })
+    }
+
+    pub fn is_subset(&self, other: &BitGrid) -> bool {
+        self.len <= other.len
+            && self
+                .chunks()
+                .all(|(y, x)| self.row_bits(y, x) & !other.row_bits(y, x) == 0)
+    }
+}
+
+#[derive(Clone)]
+pub struct Iter<'a> {
+    grid: &'a BitGrid,
+    word: usize,
+    // the bits of the current word not yet visited
+    bits: u64,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = IVec2;
+
+    fn next(&mut self) -> Option<IVec2> {
+        while self.bits == 0 {
+            self.word += 1;
+            self.bits = *self.grid.words.get(self.word)?;
+        }
+        let bit = self.bits.trailing_zeros() as i32;
+        self.bits &= self.bits - 1;
+        let row = (self.word / self.grid.stride) as i32;
+        let column = (self.word % self.grid.stride) as i32 * BITS + bit;
+        Some(self.grid.origin + IVec2::new(column, row))
+    }
+}
+
+impl<'a> IntoIterator for &'a BitGrid {
+    type Item = IVec2;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl Extend<IVec2> for BitGrid {
+    fn extend<T: IntoIterator<Item = IVec2>>(&mut self, iter: T) {
+        for cell in iter {
+            self.insert(cell);
+        }
+    }
+}
+
+impl FromIterator<IVec2> for BitGrid {
+    fn from_iter<T: IntoIterator<Item = IVec2>>(iter: T) -> Self {
+        // size the grid once up front
+        let cells = iter.into_iter().collect::<Vec<_>>();
+        let Some(&first) = cells.first() else {
+            return Self::default();
+        };
+        let (min, max) = cells.iter().fold((first, first), |(min, max), &cell| {
+            (min.min(cell), max.max(cell))
+        });
+        let mut grid = Self::with_bounds(min, max);
+        grid.extend(cells);
+        grid
+    }
+}
+
+impl PartialEq for BitGrid {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.is_subset(other)
+    }
+}
+
+impl Eq for BitGrid {}
+
+impl std::fmt::Debug for BitGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+// stored as a list of cells, the same as the HashSet was, so older saves still load
+impl Serialize for BitGrid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for BitGrid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<IVec2>::deserialize(deserializer)?
+            .into_iter()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::HashSet;
+    use rand::{Rng, SeedableRng};
+    use rand_pcg::Pcg32;
+
+    // cells spread over more than one word per row, either side of zero
+    fn random_cells(rng: &mut Pcg32, count: usize) -> HashSet<IVec2> {
+        (0..count)
+            .map(|_| IVec2::new(rng.gen_range(-90..90), rng.gen_range(-6..6)))
+            .collect()
+    }
+
+    fn check(grid: &BitGrid, oracle: &HashSet<IVec2>) {
+        assert_eq!(grid.len(), oracle.len());
+        assert_eq!(grid.iter().collect::<HashSet<_>>(), *oracle);
+        for x in -100..100 {
+            for y in -8..8 {
+                let cell = IVec2::new(x, y);
+                assert_eq!(grid.contains(&cell), oracle.contains(&cell), "{}", cell);
+            }
+        }
+        let bounds = oracle
+            .iter()
+            .fold(None, |bounds: Option<(IVec2, IVec2)>, &cell| {
+                Some(bounds.map_or((cell, cell), |(min, max)| (min.min(cell), max.max(cell))))
+            });
+        assert_eq!(grid.bounds(), bounds);
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut rng = Pcg32::seed_from_u64(1);
+        let mut grid = BitGrid::new();
+        let mut oracle = HashSet::default();
+        for _ in 0..500 {
+            let cell = IVec2::new(rng.gen_range(-90..90), rng.gen_range(-6..6));
+            match rng.gen_bool(0.7) {
+                true => assert_eq!(grid.insert(cell), oracle.insert(cell)),
+                false => assert_eq!(grid.remove(&cell), oracle.remove(&cell)),
+            }
+        }
+        check(&grid, &oracle);
+
+        for cell in oracle.clone() {
+            assert!(grid.remove(&cell));
+            oracle.remove(&cell);
+        }
+        check(&grid, &oracle);
+        assert!(grid.is_empty());
+    }
+
+    #[test]
+    fn grows_in_every_direction() {
+        let far = [
+            IVec2::new(0, 0),
+            IVec2::new(-70, 0),
+            IVec2::new(130, 1),
+            IVec2::new(3, -40),
+            IVec2::new(-2, 50),
+            IVec2::new(-200, -200),
+            IVec2::new(200, 200),
+        ];
+        let mut grid = BitGrid::new();
+        let mut oracle = HashSet::default();
+        for (i, &cell) in far.iter().enumerate() {
+            grid.insert(cell);
+            oracle.insert(cell);
+            assert_eq!(grid.iter().collect::<HashSet<_>>(), oracle, "after {}", i);
+        }
+        assert_eq!(grid.len(), far.len());
+    }
+
+    #[test]
+    fn row_bits_at_any_offset() {
+        let mut rng = Pcg32::seed_from_u64(2);
+        let oracle = random_cells(&mut rng, 400);
+        let mut grid = oracle.iter().copied().collect::<BitGrid>();
+        assert!(grid.origin.x < 0 && grid.origin.y < 0);
+
+        for shift in [IVec2::ZERO, IVec2::new(-37, 3)] {
+            grid.shift(shift);
+            for y in -10..10 {
+                for x in (-200..150).step_by(13) {
+                    let expected = (0..BITS)
+                        .filter(|i| oracle.contains(&(IVec2::new(x + i, y) - shift)))
+                        .fold(0u64, |bits, i| bits | 1 << i);
+                    assert_eq!(grid.row_bits(y, x), expected, "row {} from {}", y, x);
+                }
+            }
+            grid.shift(-shift);
+        }
+    }
+
+    #[test]
+    fn overlaps_and_touches_across_words() {
+        let mut rng = Pcg32::seed_from_u64(3);
+        for _ in 0..50 {
+            let (count_a, count_b) = (rng.gen_range(1..40), rng.gen_range(1..40));
+            let a = random_cells(&mut rng, count_a);
+            let b = random_cells(&mut rng, count_b);
+            let grid_a = a.iter().copied().collect::<BitGrid>();
+            let grid_b = b.iter().copied().collect::<BitGrid>();
+
+            let overlaps = a.iter().any(|cell| b.contains(cell));
+            let touches = b.iter().any(|cell| {
+                [IVec2::X, -IVec2::X, IVec2::Y, -IVec2::Y]
+                    .iter()
+                    .any(|n| a.contains(&(*cell + *n)))
+            });
+            assert_eq!(grid_a.overlaps(&grid_b), overlaps);
+            assert_eq!(grid_b.overlaps(&grid_a), overlaps);
+            assert_eq!(grid_a.touches(&grid_b), touches);
+            assert_eq!(grid_a.is_subset(&grid_b), a.is_subset(&b));
+        }
+
+        // neighbours either side of a word boundary, whatever the alignment
+        for origin in [-64, -1, 0, 1, 63] {
+            let a = [IVec2::new(origin, 0), IVec2::new(origin + 130, 0)]
+                .into_iter()
+                .collect::<BitGrid>();
+            for (x, touching) in [
+                (63, false),
+                (64, false),
+                (129, true),
+                (131, true),
+                (132, false),
+            ] {
+                let b = [IVec2::new(origin + x, 0)].into_iter().collect::<BitGrid>();
+                assert_eq!(a.touches(&b), touching, "{} {}", origin, x);
+                assert!(!a.overlaps(&b));
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_bounds() {
+        let mut rng = Pcg32::seed_from_u64(4);
+        let mut oracle = random_cells(&mut rng, 100);
+        let mut grid = oracle.iter().copied().collect::<BitGrid>();
+        for _ in 0..4 {
+            grid = grid.rotated();
+            oracle = oracle.iter().map(|c| IVec2::new(-c.y, c.x)).collect();
+            check(&grid, &oracle);
+        }
+    }
+
+    #[test]
+    fn equal_whatever_the_layout() {
+        let mut rng = Pcg32::seed_from_u64(5);
+        let oracle = random_cells(&mut rng, 60);
+        let tight = oracle.iter().copied().collect::<BitGrid>();
+
+        // grown one cell at a time, so a different origin and stride
+        let mut grown = BitGrid::new();
+        grown.insert(IVec2::new(500, 500));
+        grown.extend(oracle.iter().copied());
+        grown.remove(&IVec2::new(500, 500));
+        assert_ne!(grown.origin, tight.origin);
+        assert_ne!(grown.stride, tight.stride);
+        assert_eq!(grown, tight);
+
+        // moved there and back
+        let mut moved = tight.clone();
+        moved.shift(IVec2::new(3, -7));
+        assert_ne!(moved, tight);
+        moved.shift(IVec2::new(-3, 7));
+        assert_eq!(moved, tight);
+
+        let mut fewer = tight.clone();
+        fewer.remove(&tight.iter().next().unwrap());
+        assert_ne!(fewer, tight);
+        assert_ne!(tight, fewer);
+        assert_eq!(BitGrid::new(), BitGrid::empty_like(&tight));
+    }
+
+    #[test]
+    fn loads_hashset_saves() {
+        let mut rng = Pcg32::seed_from_u64(6);
+        let oracle = random_cells(&mut rng, 30);
+
+        // a save from when cells were a HashSet
+        let old = serde_json::to_string(&oracle).unwrap();
+        let grid = serde_json::from_str::<BitGrid>(&old).unwrap();
+        check(&grid, &oracle);
+
+        let again = serde_json::to_string(&grid).unwrap();
+        assert_eq!(
+            serde_json::from_str::<HashSet<IVec2>>(&again).unwrap(),
+            oracle
+        );
+        assert_eq!(
+            serde_json::from_str::<BitGrid>("[]").unwrap(),
+            BitGrid::new()
+        );
+    }
+}
@@ -0,0 +1,272 @@
+// cutting a plank along the grid lines between its cells
+
+use bevy::{prelude::*, utils::HashSet};
+
+use crate::{
+    bit_grid::BitGrid,
+    model::{neighbours, Plank},
+};
+
+// cutter positions are grid vertices. vertex v is the bottom left corner of cell v, so a cut moving
+// right from v separates cells v - Y and v, and a cut moving up from v separates cells v - X and v.
+// separated pairs are stored as (min, max) in plank coordinates.
+
+#[derive(Component, Default, Clone)]
+pub struct Cut {
+    pub visited: HashSet<IVec2>,
+    pub separated: HashSet<(IVec2, IVec2)>,
+    pub finished: bool,
+}
+
+/// result of moving the cutter one step
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CutStep {
+    /// the move was not to a neighbouring vertex, or ran outside the plank or past a finished cut
+    Blocked,
+    /// moved along the plank's edge without cutting
+    Moved,
+    /// cut between two cells. `finished` if the plank now splits in two
+    Chop { finished: bool },
+    /// retraced a cut segment. `unfinished` if the cut was finished before
+    UnChop { unfinished: bool },
+}
+
+impl Cut {
+    /// where a cut near `cursor` can start: the last cutter position if it is still adjacent, then any corner
+    /// of the cursor's cell that lies on the plank's edge or inside it
+    pub fn start_position(
+        plank: &Plank,
+        plank_pos: IVec2,
+        cursor: IVec2,
+        last: IVec2,
+    ) -> Option<IVec2> {
+        let mut positions = Vec::new();
+
+        // prefer last pos
+        let last_offset = last - cursor;
+        if last_offset.max_element() <= 1 && last_offset.min_element() >= 0 {
+            positions.push(last);
+        }
+        // then any nearby
+        let offsets = [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE];
+        for offset in offsets {
+            positions.push(cursor + offset);
+        }
+
+        positions.into_iter().find(|&pos| {
+            let count = offsets
+                .iter()
+                .filter(|&&n| plank.contains(pos + n - plank_pos - IVec2::ONE))
+                .count();
+            count > 1 && count < 4
+        })
+    }
+
+    /// move the cutter from `from` to `to` (world vertices), cutting or uncutting the plank at `plank_pos`
+    pub fn extend(&mut self, plank: &Plank, plank_pos: IVec2, from: IVec2, to: IVec2) -> CutStep {
+        let dir = to - from;
+        let affected = match (dir.x, dir.y) {
+            (1, 0) => (from - IVec2::Y, from),
+            (-1, 0) => (from - IVec2::ONE, from - IVec2::X),
+            (0, -1) => (from - IVec2::ONE, from - IVec2::Y),
+            (0, 1) => (from - IVec2::X, from),
+            _ => {
+                debug!("weird move, abort");
+                return CutStep::Blocked;
+            }
+        };
+
+        let affected = (affected.0 - plank_pos, affected.1 - plank_pos);
+
+        if !plank.contains(affected.0) && !plank.contains(affected.1) {
+            debug!("air block");
+            return CutStep::Blocked;
+        }
+
+        if self.separated.contains(&affected) {
+            debug!("unchop");
+            self.separated.remove(&affected);
+            self.visited.remove(&from);
+            let unfinished = self.finished;
+            self.finished = false;
+            return CutStep::UnChop { unfinished };
+        }
+
+        if self.finished {
+            debug!("finished block");
+            return CutStep::Blocked;
+        }
+
+        if plank.contains(affected.0) && plank.contains(affected.1) {
+            debug!("chop");
+            self.visited.insert(from);
+            self.visited.insert(to);
+            self.separated.insert(affected);
+
+            if self.is_finished(plank) {
+                debug!("finished!");
+                self.finished = true;
+            }
+            return CutStep::Chop {
+                finished: self.finished,
+            };
+        }
+
+        CutStep::Moved
+    }
+
+    pub fn split(&self, plank: &Plank) -> Option<[Plank; 2]> {
+        if self.separated.is_empty() {
+            return None;
+        }
+
+        let first = self.separated.iter().next().unwrap().0;
+        let mut connected = BitGrid::empty_like(&plank.coords);
+        connected.insert(first);
+
+        let mut to_check = Vec::new();
+        to_check.push(first);
+
+        while let Some(cur) = to_check.pop() {
+            for n in neighbours().iter() {
+                let n = *n + cur;
+                if plank.contains(n)
+                    && !self.separated.contains(&(n.min(cur), n.max(cur)))
+                    && !connected.contains(&n)
+                {
+                    connected.insert(n);
+                    to_check.push(n);
+                }
+            }
+        }
+
+        if connected.len() != plank.count() {
+            let second = plank.coords.difference(&connected).collect();
+            return Some([
+                Plank {
+                    coords: connected,
+                    turns: plank.turns,
+                    texture_offset: plank.texture_offset,
+                },
+                Plank {
+                    coords: second,
+                    turns: plank.turns,
+                    texture_offset: plank.texture_offset,
+                },
+            ]);
+        }
+
+        None
+    }
+
+    pub fn is_finished(&self, plank: &Plank) -> bool {
+        self.split(plank).is_some()
+    }
+}
+
+/// split a plank with a finished cut, renormalizing each piece so its bottom left cell is at (1, 1)
+/// like freshly spawned planks. returns the pieces with their new positions
+pub fn split_plank(cut: &Cut, plank: &Plank, pos: IVec2) -> Option<[(Plank, IVec2); 2]> {
+    let [first, second] = cut.split(plank)?;
+    let place = |mut plank: Plank| {
+        let shift = IVec2::new(-plank.extents().0 .0 + 1, -plank.extents().1 .0 + 1);
+        plank.shift(shift);
+        (plank, pos - shift)
+    };
+    Some([place(first), place(second)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plank(cells: &[(i32, i32)]) -> Plank {
+        Plank {
+            coords: cells.iter().map(|&(x, y)| IVec2::new(x, y)).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cuts_across_a_square() {
+        // a 2x2 plank at (1, 1), cut up the middle
+        let square = plank(&[(0, 0), (1, 0), (0, 1), (1, 1)]);
+        let pos = IVec2::ONE;
+        let mut cut = Cut::default();
+
+        assert_eq!(
+            cut.extend(&square, pos, IVec2::new(2, 1), IVec2::new(2, 2)),
+            CutStep::Chop { finished: false }
+        );
+        assert!(cut.split(&square).is_none());
+        assert_eq!(
+            cut.extend(&square, pos, IVec2::new(2, 2), IVec2::new(2, 3)),
+            CutStep::Chop { finished: true }
+        );
+
+        let [a, b] = cut.split(&square).unwrap();
+        assert_eq!(a.count(), 2);
+        assert_eq!(b.count(), 2);
+        assert!(!a.overlaps(&b));
+        assert_eq!(a.size().x, 1);
+        assert_eq!(b.size().x, 1);
+
+        // a finished cut can't go on, only back
+        assert_eq!(
+            cut.extend(&square, pos, IVec2::new(2, 3), IVec2::new(3, 3)),
+            CutStep::Blocked
+        );
+        assert_eq!(
+            cut.extend(&square, pos, IVec2::new(2, 3), IVec2::new(2, 2)),
+            CutStep::UnChop { unfinished: true }
+        );
+        assert!(!cut.finished);
+    }
+
+    #[test]
+    fn blocked_and_edge_moves() {
+        let bar = plank(&[(0, 0), (1, 0)]);
+        let mut cut = Cut::default();
+
+        // not a neighbouring vertex
+        assert_eq!(
+            cut.extend(&bar, IVec2::ZERO, IVec2::ZERO, IVec2::ONE),
+            CutStep::Blocked
+        );
+        // along the bottom edge
+        assert_eq!(
+            cut.extend(&bar, IVec2::ZERO, IVec2::ZERO, IVec2::X),
+            CutStep::Moved
+        );
+        // off the plank
+        assert_eq!(
+            cut.extend(&bar, IVec2::ZERO, IVec2::new(0, -1), IVec2::new(1, -1)),
+            CutStep::Blocked
+        );
+        assert!(cut.separated.is_empty());
+    }
+
+    #[test]
+    fn split_pieces_sit_where_they_were() {
+        let bar = plank(&[(0, 0), (1, 0), (2, 0)]);
+        let pos = IVec2::new(5, -2);
+        let mut cut = Cut::default();
+        cut.extend(&bar, pos, IVec2::new(6, -2), IVec2::new(6, -1));
+        assert!(cut.finished);
+
+        let pieces = split_plank(&cut, &bar, pos).unwrap();
+        let mut world = pieces
+            .iter()
+            .flat_map(|(piece, piece_pos)| piece.coords.iter().map(move |c| c + *piece_pos))
+            .collect::<Vec<_>>();
+        world.sort_by_key(|c| c.x);
+        assert_eq!(
+            world,
+            vec![IVec2::new(5, -2), IVec2::new(6, -2), IVec2::new(7, -2)]
+        );
+        for (piece, _) in pieces.iter() {
+            assert_eq!(piece.extents().0 .0, 1);
+            assert_eq!(piece.extents().1 .0, 1);
+        }
+    }
+}
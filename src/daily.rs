@@ -0,0 +1,330 @@
+// daily sets: one random set per date, seeded from the days since the first daily. every date keeps its own
+// progress so old dailies can be played from the archive, and finishing today's set extends the streak.
+
+use bevy::prelude::*;
+use bevy_carp::{
+    levels::{spawn_random, LevelSet},
+    stats::LevelStats,
+};
+use bevy_egui::{egui, EguiClipboard};
+use bevy_pkv::PkvStore;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    menus::{PopupMenu, PopupMenuEvent},
+    structs::{ActionEvent, ActionLabel},
+};
+
+const KEY_PREFIX: &str = "Daily ";
+const COMPLETED_KEY: &str = "daily completed";
+const STREAK_KEY: &str = "daily streak";
+// progress from before dailies were kept per date
+const OLD_KEY: &str = "Daily";
+const OLD_DATE_KEY: &str = "current daily date";
+const ARCHIVE_PAGE_SIZE: usize = 10;
+const ARCHIVE_LABELS: [&str; ARCHIVE_PAGE_SIZE] = [
+    "daily archive 0",
+    "daily archive 1",
+    "daily archive 2",
+    "daily archive 3",
+    "daily archive 4",
+    "daily archive 5",
+    "daily archive 6",
+    "daily archive 7",
+    "daily archive 8",
+    "daily archive 9",
+];
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_archive_menu).add_system(spawn_summary);
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub struct DailyStreak {
+    pub current: usize,
+    pub best: usize,
+    // the last date finished on the day
+    pub last: Option<NaiveDate>,
+}
+
+impl DailyStreak {
+    pub fn get(settings: &PkvStore) -> Self {
+        settings.get(STREAK_KEY).unwrap_or_default()
+    }
+
+    /// the streak as of `today`, broken if yesterday's set wasn't finished
+    pub fn current_on(&self, today: NaiveDate) -> usize {
+        match self.last {
+            Some(last) if last >= today.pred() => self.current,
+            _ => 0,
+        }
+    }
+}
+
+pub fn today() -> NaiveDate {
+    chrono::Utc::today().naive_utc()
+}
+
+fn first_daily() -> NaiveDate {
+    NaiveDate::from_ymd(2022, 6, 1)
+}
+
+/// the set for a date, with that date's own progress key
+pub fn daily_set(date: NaiveDate, settings: &mut PkvStore) -> LevelSet {
+    let seed = date.signed_duration_since(first_daily()).num_days() * 1068;
+    let key = format!("{}{}", KEY_PREFIX, date);
+
+    if settings.get::<usize>(&key).is_err() {
+        let old_date = settings.get::<NaiveDate>(OLD_DATE_KEY).ok();
+        let progress = match old_date == Some(date) {
+            true => settings.get::<usize>(OLD_KEY).unwrap_or_default(),
+            false => 0,
+        };
+        settings.set(&key, &progress).unwrap();
+    }
+
+    spawn_random(
+        30,
+        0,
+        30,
+        format!("Daily Set for {}", date),
+        seed as u64,
+        // keeps old days' sets as they were played
+        0,
+        key,
+    )
+}
+
+/// the date of a daily set
+pub fn set_date(set: &LevelSet) -> Option<NaiveDate> {
+    set.settings_key.strip_prefix(KEY_PREFIX)?.parse().ok()
+}
+
+fn completed(settings: &PkvStore) -> Vec<NaiveDate> {
+    settings.get(COMPLETED_KEY).unwrap_or_default()
+}
+
+/// record a finished daily set, returning the streak
+pub fn complete(settings: &mut PkvStore, date: NaiveDate) -> DailyStreak {
+    let mut dates = completed(settings);
+    if !dates.contains(&date) {
+        dates.push(date);
+        dates.sort();
+        settings.set(COMPLETED_KEY, &dates).unwrap();
+    }
+
+    let mut streak = DailyStreak::get(settings);
+    // archive dates don't count, only finishing a set on its day
+    if date == today() && streak.last != Some(date) {
+        streak.current = streak.current_on(date) + 1;
+        streak.best = streak.best.max(streak.current);
+        streak.last = Some(date);
+        settings.set(STREAK_KEY, &streak).unwrap();
+    }
+    streak
+}
+
+fn spawn_archive_menu(
+    mut evs: EventReader<ActionEvent>,
+    mut spawn: EventWriter<PopupMenuEvent>,
+    mut actions: EventWriter<ActionEvent>,
+    mut settings: ResMut<PkvStore>,
+    mut set: ResMut<LevelSet>,
+    mut page: Local<usize>,
+) {
+    let today = today();
+    let archive_days = today.signed_duration_since(first_daily()).num_days().max(0) as usize;
+    let pages = archive_days.div_ceil(ARCHIVE_PAGE_SIZE);
+    // days before today, newest first
+    let slot_date = |page: usize, slot: usize| {
+        let days_ago = page * ARCHIVE_PAGE_SIZE + slot + 1;
+        (days_ago <= archive_days).then(|| today - chrono::Duration::days(days_ago as i64))
+    };
+
+    for ev in evs.iter() {
+        match ev.label.0 {
+            "daily archive" => *page = 0,
+            "newer dailies" => *page = page.saturating_sub(1),
+            "older dailies" => *page = (*page + 1).min(pages.saturating_sub(1)),
+            label => {
+                let Some(slot) = ARCHIVE_LABELS.iter().position(|l| *l == label) else {
+                    continue;
+                };
+                let Some(date) = slot_date(*page, slot) else {
+                    continue;
+                };
+                *set = daily_set(date, &mut settings);
+                actions.send(ActionEvent {
+                    sender: ev.sender,
+                    label: ActionLabel("play current set"),
+                    target: None,
+                });
+                continue;
+            }
+        }
+
+        let done = completed(&settings);
+        let mut items = (0..ARCHIVE_PAGE_SIZE)
+            .filter_map(|slot| {
+                let date = slot_date(*page, slot)?;
+                let text = match done.contains(&date) {
+                    true => format!("{} - done", date),
+                    false => date.to_string(),
+                };
+                Some((text.into(), ActionLabel(ARCHIVE_LABELS[slot]), true))
+            })
+            .collect::<Vec<_>>();
+        items.push(("Newer".into(), ActionLabel("newer dailies"), *page > 0));
+        items.push((
+            "Older".into(),
+            ActionLabel("older dailies"),
+            *page + 1 < pages,
+        ));
+
+        let streak = DailyStreak::get(&settings);
+        spawn.send(PopupMenuEvent {
+            sender: ev.sender,
+            menu: PopupMenu {
+                heading: format!(
+                    "Daily Archive\nstreak {} (best {}), {} finished",
+                    streak.current_on(today),
+                    streak.best,
+                    done.len()
+                ),
+                items,
+                cancel_action: Some(ActionLabel("play")),
+                width: 2,
+                text_size: 30.0,
+                ..Default::default()
+            },
+            sound: false,
+        });
+    }
+}
+
+/// each level's best result in a daily set, `None` for levels not finished
+fn set_results(set: &LevelSet, settings: &PkvStore) -> Vec<Option<LevelStats>> {
+    set.levels
+        .iter()
+        .map(|def| {
+            settings
+                .get(&LevelStats::best_key(&set.settings_key, def))
+                .ok()
+        })
+        .collect()
+}
+
+/// a copyable block with a square per level coloured by stars, in rows of 10
+fn share_text(date: NaiveDate, results: &[Option<LevelStats>]) -> String {
+    let grid = results
+        .chunks(10)
+        .map(|row| {
+            row.iter()
+                .map(|result| match result.map(|stats| stats.stars()) {
+                    Some(3) => '🟩',
+                    Some(2) => '🟨',
+                    Some(_) => '🟧',
+                    None => '⬛',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let done = results.iter().flatten();
+    let (cuts, par, undos, time) = done.fold((0, 0, 0, 0.0), |(c, p, u, t), stats| {
+        (
+            c + stats.cuts,
+            p + stats.par,
+            u + stats.undos,
+            t + stats.time,
+        )
+    });
+    format!(
+        "Measure Once daily {}\n{}\n{} cuts (par {}), {} undos, {}:{:02}",
+        date,
+        grid,
+        cuts,
+        par,
+        undos,
+        time as u32 / 60,
+        time as u32 % 60
+    )
+}
+
+fn spawn_summary(
+    mut evs: EventReader<ActionEvent>,
+    mut spawn: EventWriter<PopupMenuEvent>,
+    mut clipboard: ResMut<EguiClipboard>,
+    settings: Res<PkvStore>,
+    set: Res<LevelSet>,
+) {
+    for ev in evs.iter() {
+        let copied = match ev.label.0 {
+            "daily summary" => false,
+            "copy daily results" => true,
+            _ => continue,
+        };
+        let Some(date) = set_date(&set) else {
+            continue;
+        };
+
+        let results = set_results(&set, &settings);
+        let text = share_text(date, &results);
+        if copied {
+            clipboard.set_contents(&text);
+        }
+
+        let mut items = results
+            .iter()
+            .enumerate()
+            .map(|(i, result)| {
+                let text = match result {
+                    Some(stats) => format!(
+                        "{}: {}* {}c {}u {:.0}s",
+                        i + 1,
+                        stats.stars(),
+                        stats.cuts,
+                        stats.undos,
+                        stats.time
+                    ),
+                    None => format!("{}: -", i + 1),
+                };
+                (text.into(), ActionLabel(""), false)
+            })
+            .collect::<Vec<_>>();
+        items.push((
+            match copied {
+                true => "Copied!",
+                false => "Copy Results",
+            }
+            .into(),
+            ActionLabel("copy daily results"),
+            true,
+        ));
+        items.push(("".into(), ActionLabel(""), false));
+        items.push(("Main Menu".into(), ActionLabel("main menu"), true));
+
+        // the menu font has no emoji, show the totals without the grid
+        let totals = text.lines().last().unwrap_or_default();
+        spawn.send(PopupMenuEvent {
+            sender: ev.sender,
+            menu: PopupMenu {
+                heading: format!("Daily Summary {}\n{}", date, totals),
+                items,
+                cancel_action: Some(ActionLabel("main menu")),
+                width: 3,
+                initial_position: if copied { -1 } else { 0 },
+                inactive_color: egui::Color32::from_rgb(255, 255, 255),
+                text_size: 20.0,
+                ..Default::default()
+            },
+            sound: false,
+        });
+    }
+}
@@ -0,0 +1,87 @@
+// when a plank in hand or under the cursor covers a hole without filling it, mark the cells hanging over the
+// edge and the cells of the hole left empty
+
+use bevy::prelude::*;
+use bevy_carp::model::Level;
+
+use crate::{
+    bl_quad::BLQuad, hint::overlay_material, structs::Position, MHoles, PlankComponent, Selected,
+    Targeted, PLANK_Z_SELECTED,
+};
+
+pub struct DiffPlugin;
+
+impl Plugin for DiffPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(show_diff);
+    }
+}
+
+#[derive(Component)]
+struct DiffCell;
+
+struct DiffAssets {
+    cell: Handle<Mesh>,
+    overhang: Handle<StandardMaterial>,
+    missing: Handle<StandardMaterial>,
+}
+
+fn show_diff(
+    mut commands: Commands,
+    level: Res<Level>,
+    selected: Query<(Entity, &PlankComponent, &Position), With<Selected>>,
+    targeted: Query<(Entity, &PlankComponent, &Position), With<Targeted>>,
+    holes: Query<&Position, With<MHoles>>,
+    cells: Query<Entity, With<DiffCell>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<DiffAssets>>,
+    // the plank, where it was, its turns and the holes left, to tell when to look again
+    mut checked: Local<Option<(Entity, Position, usize, usize)>>,
+) {
+    let focus = selected
+        .get_single()
+        .or_else(|_| targeted.get_single())
+        .ok();
+    let current =
+        focus.map(|(ent, plank, pos)| (ent, *pos, plank.0.turns, level.holes.holes.len()));
+    if current == *checked {
+        return;
+    }
+
+    *checked = current;
+    for ent in cells.iter() {
+        commands.entity(ent).despawn();
+    }
+
+    let (Some((_, plank, pos)), Ok(hole_pos)) = (focus, holes.get_single()) else {
+        return;
+    };
+    let Some(diff) = level.placement_diff(&plank.0, *pos) else {
+        return;
+    };
+
+    let assets = assets.get_or_insert_with(|| DiffAssets {
+        cell: meshes.add(BLQuad::new(Vec2::new(0.8, 0.8), Vec2::ZERO).into()),
+        overhang: overlay_material(&mut materials, Color::rgba(1.0, 0.2, 0.2, 0.6)),
+        missing: overlay_material(&mut materials, Color::rgba(1.0, 0.9, 0.3, 0.5)),
+    });
+
+    let cells = diff
+        .overhang
+        .iter()
+        .map(|cell| (cell, &assets.overhang))
+        .chain(diff.missing.iter().map(|cell| (cell, &assets.missing)));
+    for (cell, material) in cells {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: assets.cell.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(
+                    ((*cell + hole_pos.0).as_vec2() + 0.1).extend(PLANK_Z_SELECTED + 0.02),
+                ),
+                ..Default::default()
+            })
+            .insert(DiffCell);
+    }
+}
@@ -0,0 +1,332 @@
+// paint a level's plank and holes, then test play it or save it as a level file.
+// the editor shows its cells through the normal level rendering: the holes as the level's hole board and the
+// plank as a done plank, and repaints by resetting the level.
+
+use bevy::{math::const_ivec2, prelude::*};
+use bevy_carp::{
+    level_file::LevelFile,
+    levels::{LevelDef, LevelSet},
+    model::{CoordSet, Hole, Holes, Level, Plank, UndoBuffer},
+};
+use bevy_pkv::PkvStore;
+
+use crate::{
+    input::{ActionType, Controller, DisplayMode},
+    menus::{PopupMenu, PopupMenuEvent},
+    structs::{ActionEvent, ActionLabel, Position, PositionZ, SpawnLevelEvent},
+    Cursor, DonePlanks, ResetEvent, SystemController,
+};
+
+// level set key while test playing, the pause and win menus offer a way back to the editor
+pub const EDITOR_KEY: &str = "Editor";
+// smallest hole area to paint in
+const EDITOR_SIZE: IVec2 = const_ivec2!([16, 8]);
+const SAVE_KEY: &str = "editor level";
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_FILE: &str = "editor.level.json";
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Editor>()
+            .add_system(editor_actions)
+            .add_system(editor_controls.after(crate::check_cut_actions))
+            .add_system(spawn_editor_menu);
+    }
+}
+
+pub struct Editor {
+    pub active: bool,
+    // size of the hole area
+    pub extents: IVec2,
+    // world cells
+    pub plank: Plank,
+    // hole area cells, (1, 1) is the bottom left of the area
+    pub holes: Hole,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            active: false,
+            extents: EDITOR_SIZE,
+            plank: Default::default(),
+            holes: Default::default(),
+        }
+    }
+}
+
+impl Editor {
+    fn level(&self) -> Level {
+        Level {
+            extents: self.extents,
+            holes: Holes {
+                holes: vec![self.holes.clone()],
+            },
+            planks: Vec::new(),
+            setup: true,
+            ..Default::default()
+        }
+    }
+
+    fn to_file(&self) -> LevelFile {
+        LevelFile::from_cells("Editor Level".into(), &self.plank, &self.holes)
+    }
+
+    // unfinished levels are loaded too, so this skips the checks in `LevelFile::to_level`
+    fn load(&mut self, file: &LevelFile) {
+        let mut plank = file.plank.to_cells().unwrap_or_default();
+        let size = plank.size() + 1;
+        plank.shift(IVec2::new(-size.x / 2, -size.y - 1) + IVec2::ONE);
+
+        let holes = file
+            .holes
+            .iter()
+            .filter_map(|hole| {
+                let mut coords = hole.shape.to_cells().ok()?;
+                coords.shift(IVec2::from(hole.at));
+                Some(coords)
+            })
+            .collect::<Vec<_>>();
+        let holes = CoordSet::merge(holes.iter());
+        let ((_, max_x), (_, max_y)) = holes.extents();
+
+        self.extents = EDITOR_SIZE.max(IVec2::new(max_x, max_y));
+        self.plank = plank;
+        self.holes = holes;
+    }
+
+    fn clear(&mut self) {
+        *self = Self {
+            active: self.active,
+            ..Default::default()
+        };
+    }
+}
+
+#[derive(Component)]
+pub struct EditorControls;
+
+fn editor_actions(
+    mut evs: EventReader<ActionEvent>,
+    mut editor: ResMut<Editor>,
+    mut commands: Commands,
+    mut level: ResMut<Level>,
+    mut done_planks: ResMut<DonePlanks>,
+    mut set: ResMut<LevelSet>,
+    mut reset: EventWriter<ResetEvent>,
+    mut spawn_level: EventWriter<SpawnLevelEvent>,
+    mut popup: EventWriter<PopupMenuEvent>,
+    mut settings: ResMut<PkvStore>,
+    cursor: Query<(&Transform, &Position), With<Cursor>>,
+    camera: Query<(&Transform, &Position, &PositionZ), With<Camera>>,
+) {
+    let mut changed = false;
+    let mut entering = false;
+    let mut message = None;
+
+    for ev in evs.iter() {
+        match ev.label.0 {
+            "editor" => {
+                editor.active = true;
+                commands.insert_resource(UndoBuffer::new(editor.level()));
+                entering = true;
+                changed = true;
+            }
+            "main menu" => editor.active = false,
+            _ if !editor.active => (),
+            "paint" => {
+                let Ok((_, cursor)) = cursor.get(ev.sender) else {
+                    continue;
+                };
+                let cell = cursor.0 - editor.level().hole_origin();
+                let in_holes = cell.cmpge(IVec2::ONE).all() && cell.cmple(editor.extents).all();
+                if in_holes {
+                    if !editor.holes.coords.remove(&cell) {
+                        editor.holes.coords.insert(cell);
+                    }
+                } else if cursor.0.y < 0 {
+                    if !editor.plank.coords.remove(&cursor.0) {
+                        editor.plank.coords.insert(cursor.0);
+                    }
+                } else {
+                    continue;
+                }
+                changed = true;
+            }
+            "clear level" => {
+                editor.clear();
+                changed = true;
+            }
+            "save level" => {
+                let file = editor.to_file();
+                settings.set(SAVE_KEY, &file).unwrap();
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Err(e) =
+                    std::fs::write(SAVE_FILE, serde_json::to_string_pretty(&file).unwrap())
+                {
+                    warn!("failed to write {}: {}", SAVE_FILE, e);
+                }
+                message = Some("Saved".to_string());
+            }
+            "load level" => {
+                #[cfg(not(target_arch = "wasm32"))]
+                let from_file = std::fs::read(SAVE_FILE)
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice::<LevelFile>(&bytes).ok());
+                #[cfg(target_arch = "wasm32")]
+                let from_file = None;
+
+                match from_file.or_else(|| settings.get::<LevelFile>(SAVE_KEY).ok()) {
+                    Some(file) => {
+                        editor.load(&file);
+                        changed = true;
+                    }
+                    None => message = Some("Nothing saved yet".to_string()),
+                }
+            }
+            "test level" => {
+                let file = editor.to_file();
+                match file.to_level() {
+                    Ok(_) => {
+                        let def = LevelDef {
+                            num_holes: file.holes.len(),
+                            total_blocks: editor.plank.count(),
+                            seed: 0,
+                            file: Some(file),
+                        };
+                        *set = LevelSet {
+                            levels: vec![def.clone()],
+                            current_level: 0,
+                            title: "Editor Level".into(),
+                            settings_key: EDITOR_KEY.into(),
+                            recipe: None,
+                        };
+                        editor.active = false;
+                        spawn_level.send(SpawnLevelEvent { def });
+                        return;
+                    }
+                    Err(e) => message = Some(format!("Can't play this yet:\n{}", e)),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if let Some(heading) = message {
+        popup.send(PopupMenuEvent {
+            sender: Entity::from_raw(0),
+            menu: PopupMenu {
+                heading,
+                items: vec![("Ok".into(), ActionLabel("cancel"), true)],
+                cancel_action: Some(ActionLabel("cancel")),
+                ..Default::default()
+            },
+            sound: false,
+        });
+    }
+
+    if !changed || !editor.active {
+        return;
+    }
+
+    *level = editor.level();
+    done_planks.0.clear();
+    if editor.plank.count() > 0 {
+        done_planks
+            .0
+            .push((editor.plank.clone(), Position::default(), Vec::new()));
+    }
+
+    if entering {
+        reset.send_default();
+        return;
+    }
+
+    // repaint in place, keeping the cursor and camera where they are
+    if let (Ok((&cursor_trans, &cursor_pos)), Ok((&camera_trans, &camera_pos, &camera_z))) =
+        (cursor.get_single(), camera.get_single())
+    {
+        reset.send(ResetEvent {
+            cursor_pos: Some(cursor_pos),
+            cursor_trans: Some(cursor_trans),
+            camera_pos: Some((camera_pos, camera_z)),
+            camera_trans: Some(camera_trans),
+        });
+    } else {
+        reset.send_default();
+    }
+}
+
+// swap the level controls for the editor's once `create_level` has spawned them
+fn editor_controls(
+    mut commands: Commands,
+    editor: Res<Editor>,
+    mut controllers: Query<(Entity, &mut Controller, Option<&Cursor>), Without<EditorControls>>,
+    system: Query<(), With<SystemController>>,
+) {
+    if !editor.active {
+        return;
+    }
+
+    for (ent, mut controller, cursor) in controllers.iter_mut() {
+        if cursor.is_some() {
+            for (typ, action) in controller.actions.iter_mut() {
+                match typ {
+                    ActionType::MainAction => {
+                        action.label = ActionLabel("paint");
+                        action.display = DisplayMode::Active;
+                    }
+                    ActionType::SecondAction => {
+                        action.label = ActionLabel("test level");
+                        action.display = DisplayMode::Active;
+                    }
+                    _ => (),
+                }
+            }
+        } else if system.get(ent).is_ok() {
+            controller
+                .actions
+                .retain(|(typ, _)| typ == &ActionType::Menu);
+            for (_, action) in controller.actions.iter_mut() {
+                action.label = ActionLabel("editor menu");
+            }
+        } else {
+            continue;
+        }
+        commands.entity(ent).insert(EditorControls);
+    }
+}
+
+fn spawn_editor_menu(
+    mut evs: EventReader<ActionEvent>,
+    editor: Res<Editor>,
+    mut spawn: EventWriter<PopupMenuEvent>,
+) {
+    for ev in evs.iter() {
+        if ev.label.0 == "editor menu" {
+            spawn.send(PopupMenuEvent {
+                sender: ev.sender,
+                menu: PopupMenu {
+                    heading: format!(
+                        "Level Editor\nplank {} / holes {}",
+                        editor.plank.count(),
+                        editor.holes.count()
+                    ),
+                    items: vec![
+                        ("Resume".into(), ActionLabel("cancel"), true),
+                        ("Test Play".into(), ActionLabel("test level"), true),
+                        ("Save".into(), ActionLabel("save level"), true),
+                        ("Load".into(), ActionLabel("load level"), true),
+                        ("Clear".into(), ActionLabel("clear level"), true),
+                        ("Main Menu".into(), ActionLabel("main menu"), true),
+                    ],
+                    cancel_action: Some(ActionLabel("cancel")),
+                    ..Default::default()
+                },
+                sound: true,
+            })
+        }
+    }
+}
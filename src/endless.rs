@@ -0,0 +1,52 @@
+// endless runs: the set grows by one generated level whenever its last level starts, so there is always a next
+// level. the run length and best run are kept in the settings.
+
+use bevy::prelude::*;
+use bevy_carp::levels::{next_endless, LevelSet};
+use bevy_pkv::PkvStore;
+
+use crate::structs::SpawnLevelEvent;
+
+pub const ENDLESS_KEY: &str = "Endless";
+const RUN_KEY: &str = "endless run";
+const BEST_RUN_KEY: &str = "endless best run";
+
+pub struct EndlessPlugin;
+
+impl Plugin for EndlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(extend_endless);
+    }
+}
+
+/// store a finished run length, returning the best run so far
+pub fn record_run(settings: &mut PkvStore, run: usize) -> usize {
+    let best = settings
+        .get::<usize>(BEST_RUN_KEY)
+        .unwrap_or_default()
+        .max(run);
+    settings.set(RUN_KEY, &run).unwrap();
+    settings.set(BEST_RUN_KEY, &best).unwrap();
+    best
+}
+
+pub fn best_run(settings: &PkvStore) -> usize {
+    settings.get(BEST_RUN_KEY).unwrap_or_default()
+}
+
+fn extend_endless(mut spawn_evs: EventReader<SpawnLevelEvent>, mut set: ResMut<LevelSet>) {
+    if spawn_evs.iter().count() == 0 || set.settings_key != ENDLESS_KEY {
+        return;
+    }
+
+    if set.current_level + 1 >= set.levels.len() {
+        let next = next_endless(set.levels.last().unwrap());
+        debug!(
+            "endless level {}: {} holes, {} blocks",
+            set.levels.len() + 1,
+            next.num_holes,
+            next.total_blocks
+        );
+        set.levels.push(next);
+    }
+}
@@ -0,0 +1,107 @@
+// outline the holes the plank in hand or under the cursor would fill, and say how many turns it needs
+
+use bevy::prelude::*;
+use bevy_carp::model::Level;
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    hint::{outline, spawn_outline, OutlineAssets},
+    structs::Position,
+    LevelControls, MHoles, PlankComponent, Selected, Targeted,
+};
+
+pub struct FitPlugin;
+
+impl Plugin for FitPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Fits>()
+            .add_system(find_fits)
+            .add_system(show_fits.after(find_fits));
+    }
+}
+
+#[derive(Component, Clone)]
+struct FitOutline;
+
+#[derive(Default)]
+struct Fits {
+    // the plank, its turns and the holes left, to tell when to look again
+    checked: Option<(Entity, usize, usize)>,
+    // in hand rather than under the cursor
+    held: bool,
+    // matching holes and the quarter turns anticlockwise each needs
+    holes: Vec<(usize, usize)>,
+}
+
+fn find_fits(
+    mut commands: Commands,
+    mut fits: ResMut<Fits>,
+    level: Res<Level>,
+    selected: Query<(Entity, &PlankComponent), With<Selected>>,
+    targeted: Query<(Entity, &PlankComponent), With<Targeted>>,
+    holes: Query<&Position, With<MHoles>>,
+    outlines: Query<Entity, With<FitOutline>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<OutlineAssets>>,
+) {
+    let held = selected.get_single().ok();
+    let focus = held.or_else(|| targeted.get_single().ok());
+    let checked = focus.map(|(ent, plank)| (ent, plank.0.turns, level.holes.holes.len()));
+    if checked == fits.checked {
+        return;
+    }
+
+    fits.checked = checked;
+    fits.held = held.is_some();
+    fits.holes.clear();
+    for ent in outlines.iter() {
+        commands.entity(ent).despawn();
+    }
+
+    let (Some((_, plank)), Ok(hole_pos)) = (focus, holes.get_single()) else {
+        return;
+    };
+    fits.holes = level
+        .holes
+        .holes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, hole)| Some((i, plank.0.turns_to_fit(hole)?)))
+        .collect();
+
+    let assets = assets.get_or_insert_with(|| {
+        OutlineAssets::new(&mut meshes, &mut materials, Color::rgba(0.5, 1.0, 0.5, 0.6))
+    });
+    for &(i, _) in fits.holes.iter() {
+        spawn_outline(
+            &mut commands,
+            assets,
+            outline(&level.holes.holes[i], hole_pos.0),
+            FitOutline,
+        );
+    }
+}
+
+fn show_fits(mut egui_context: ResMut<EguiContext>, fits: Res<Fits>, controls: LevelControls) {
+    if fits.checked.is_none() || !controls.enabled() {
+        return;
+    }
+
+    let text = match fits.holes.iter().map(|(_, turns)| *turns).min() {
+        // only worth saying for the plank in hand
+        None if !fits.held => return,
+        None => "No hole this shape".to_string(),
+        Some(0) => "Fits!".to_string(),
+        Some(1) => "Fits after 1 turn".to_string(),
+        Some(turns) => format!("Fits after {} turns", turns),
+    };
+
+    egui::Window::new("fit")
+        .anchor(egui::Align2::CENTER_TOP, (0.0, 5.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label(text);
+        });
+}
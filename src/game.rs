@@ -0,0 +1,280 @@
+// the rules of a level as a plain state machine, for playing without the ecs.
+// the game systems use the same helpers (`Cut::extend`, `split_plank`, `Level::matching_hole`, ...)
+// so both follow the same rules.
+
+use bevy::prelude::*;
+
+use crate::{
+    cut::{split_plank, Cut, CutStep},
+    levels::{build_level, LevelDef},
+    model::{Level, Plank, Position},
+};
+
+#[derive(Clone)]
+pub struct Cutting {
+    pub plank: usize,
+    pub cut: Cut,
+    // cutter vertex in world coordinates
+    pub position: IVec2,
+}
+
+#[derive(Clone)]
+pub struct Puzzle {
+    // `level.planks` are the loose planks, `level.holes` the unfilled holes
+    pub level: Level,
+    pub done: Vec<(Plank, Position)>,
+    pub held: Option<usize>,
+    pub cutting: Option<Cutting>,
+    last_cut_position: IVec2,
+}
+
+impl Puzzle {
+    pub fn new(level: Level) -> Self {
+        Self {
+            level,
+            done: Vec::new(),
+            held: None,
+            cutting: None,
+            last_cut_position: IVec2::ZERO,
+        }
+    }
+
+    pub fn from_def(def: &LevelDef) -> Self {
+        Self::new(build_level(def))
+    }
+
+    /// the loose plank covering a world cell
+    pub fn plank_at(&self, cell: IVec2) -> Option<usize> {
+        self.level
+            .planks
+            .iter()
+            .position(|(plank, pos)| plank.contains(cell - pos.0))
+    }
+
+    pub fn grab(&mut self, plank: usize) -> bool {
+        if self.held.is_some() || self.cutting.is_some() || plank >= self.level.planks.len() {
+            return false;
+        }
+        self.held = Some(plank);
+        true
+    }
+
+    /// put down the held plank. returns the hole it filled, if any
+    pub fn drop(&mut self) -> Option<usize> {
+        self.held.take()?;
+        self.hammer()
+    }
+
+    /// drop the held plank and pick up another
+    pub fn swap(&mut self, plank: usize) -> Option<usize> {
+        let held = self.held?;
+        if plank == held || plank >= self.level.planks.len() {
+            return None;
+        }
+        let target = self.level.planks[plank].clone();
+        let filled = self.drop();
+        // filling a hole removes the dropped plank, find the one we want again
+        let plank = self
+            .level
+            .planks
+            .iter()
+            .position(|other| other == &target)?;
+        self.grab(plank);
+        filled
+    }
+
+    pub fn move_held(&mut self, by: IVec2) -> bool {
+        let Some(held) = self.held else {
+            return false;
+        };
+        self.level.planks[held].1 .0 += by;
+        true
+    }
+
+    /// rotate the held plank a quarter turn anticlockwise per turn, about the `pivot` cell
+    pub fn rotate_held(&mut self, pivot: IVec2, turns: usize) -> bool {
+        let Some(held) = self.held else {
+            return false;
+        };
+        let (plank, pos) = &mut self.level.planks[held];
+        for _ in 0..turns {
+            plank.rotate_about(pos, pivot);
+        }
+        true
+    }
+
+    /// start cutting the plank under `cursor`, from a vertex of the cursor's cell
+    pub fn begin_cut(&mut self, cursor: IVec2) -> bool {
+        if self.held.is_some() || self.cutting.is_some() {
+            return false;
+        }
+        let Some(plank) = self.plank_at(cursor) else {
+            return false;
+        };
+        let (coords, pos) = &self.level.planks[plank];
+        let Some(position) = Cut::start_position(coords, pos.0, cursor, self.last_cut_position)
+        else {
+            return false;
+        };
+        self.cutting = Some(Cutting {
+            plank,
+            cut: Cut::default(),
+            position,
+        });
+        true
+    }
+
+    /// move the cutter to a neighbouring vertex
+    pub fn extend_cut(&mut self, to: IVec2) -> CutStep {
+        let Some(cutting) = self.cutting.as_mut() else {
+            return CutStep::Blocked;
+        };
+        let (plank, pos) = &self.level.planks[cutting.plank];
+        let step = cutting.cut.extend(plank, pos.0, cutting.position, to);
+        if step != CutStep::Blocked {
+            cutting.position = to;
+        }
+        step
+    }
+
+    pub fn cancel_cut(&mut self) {
+        if let Some(cutting) = self.cutting.take() {
+            self.last_cut_position = cutting.position;
+        }
+    }
+
+    /// split the plank along a finished cut. returns the indices of the two new planks
+    pub fn finish_cut(&mut self) -> Option<[usize; 2]> {
+        let cutting = self.cutting.as_ref()?;
+        if !cutting.cut.finished {
+            return None;
+        }
+        let (plank, pos) = &self.level.planks[cutting.plank];
+        let pieces = split_plank(&cutting.cut, plank, pos.0)?;
+
+        let cutting = self.cutting.take().unwrap();
+        self.last_cut_position = cutting.position;
+        self.level.planks.remove(cutting.plank);
+        let first = self.level.planks.len();
+        for (plank, pos) in pieces {
+            self.level.planks.push((plank, Position(pos)));
+        }
+        Some([first, first + 1])
+    }
+
+    /// fix the first loose plank that exactly fills a hole. returns the filled hole
+    pub fn hammer(&mut self) -> Option<usize> {
+        let (plank, hole) = self
+            .level
+            .planks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| Some(*i) != self.held)
+            .find_map(|(i, (plank, pos))| Some((i, self.level.matching_hole(plank, *pos)?)))?;
+
+        self.level.holes.holes.remove(hole);
+        let done = self.level.planks.remove(plank);
+        self.done.push(done);
+        if let Some(held) = self.held.as_mut() {
+            if *held > plank {
+                *held -= 1;
+            }
+        }
+        Some(hole)
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.level.is_won()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Hole, Holes};
+
+    fn cells(cells: &[(i32, i32)]) -> Plank {
+        Plank {
+            coords: cells.iter().map(|&(x, y)| IVec2::new(x, y)).collect(),
+            ..Default::default()
+        }
+    }
+
+    // a two cell plank below two single cell holes
+    fn domino() -> Puzzle {
+        Puzzle::new(Level {
+            holes: Holes {
+                holes: vec![cells(&[(0, 0)]) as Hole, cells(&[(2, 0)])],
+            },
+            planks: vec![(cells(&[(0, 0), (1, 0)]), Position(IVec2::new(0, -3)))],
+            ..Default::default()
+        })
+    }
+
+    // move the held plank so its first cell lands on the first cell of `hole`
+    fn move_onto(puzzle: &mut Puzzle, hole: usize) {
+        let (plank, pos) = &puzzle.level.planks[puzzle.held.unwrap()];
+        let from = plank.coords.iter().next().unwrap() + pos.0;
+        let to = puzzle.level.holes.holes[hole].coords.iter().next().unwrap()
+            + puzzle.level.hole_origin();
+        assert!(puzzle.move_held(to - from));
+    }
+
+    #[test]
+    fn cut_and_place() {
+        let mut puzzle = domino();
+        assert!(puzzle.finish_cut().is_none());
+
+        assert!(puzzle.begin_cut(IVec2::new(1, -3)));
+        assert!(!puzzle.grab(0));
+        assert_eq!(
+            puzzle.extend_cut(IVec2::new(1, -2)),
+            CutStep::Chop { finished: true }
+        );
+        assert_eq!(puzzle.finish_cut(), Some([0, 1]));
+        assert_eq!(puzzle.level.planks.len(), 2);
+
+        while !puzzle.level.planks.is_empty() {
+            assert!(puzzle.grab(0));
+            move_onto(&mut puzzle, 0);
+            assert_eq!(puzzle.drop(), Some(0));
+        }
+        assert_eq!(puzzle.done.len(), 2);
+        assert!(puzzle.is_won());
+    }
+
+    #[test]
+    fn misses_stay_loose() {
+        let mut puzzle = domino();
+        assert!(puzzle.grab(0));
+        // the whole plank is too big for either hole
+        move_onto(&mut puzzle, 0);
+        assert_eq!(puzzle.drop(), None);
+        assert_eq!(puzzle.level.planks.len(), 1);
+        assert!(puzzle.held.is_none());
+        assert!(!puzzle.is_won());
+    }
+
+    #[test]
+    fn cancelled_cut_leaves_plank_whole() {
+        let mut puzzle = domino();
+        assert!(puzzle.begin_cut(IVec2::new(1, -3)));
+        puzzle.extend_cut(IVec2::new(1, -2));
+        puzzle.cancel_cut();
+        assert!(puzzle.cutting.is_none());
+        assert!(puzzle.finish_cut().is_none());
+        assert_eq!(puzzle.level.planks.len(), 1);
+    }
+
+    #[test]
+    fn rotate_about_pivot() {
+        let mut puzzle = domino();
+        assert!(puzzle.grab(0));
+        // the plank's first cell stays put
+        assert!(puzzle.rotate_held(IVec2::new(0, -3), 1));
+        let (plank, pos) = &puzzle.level.planks[0];
+        let mut world = plank.coords.iter().map(|c| c + pos.0).collect::<Vec<_>>();
+        world.sort_by_key(|c| c.y);
+        assert_eq!(world, vec![IVec2::new(0, -3), IVec2::new(0, -2)]);
+    }
+}
@@ -0,0 +1,417 @@
+// the level systems without a window, egui, audio or rendering. `headless_app` is stepped with `App::update`
+// by tests, which send `SpawnLevelEvent`s and `ActionEvent`s and check `Level`, `DonePlanks` and `UndoBuffer`.
+// `bevy_carp --headless --replay <file>` plays a replay's actions through it, waits for the last cut / hammer
+// to settle, then prints the final state as json and exits. the exit code is 0 if the level was won.
+
+use std::time::Duration;
+
+use bevy::{app::ScheduleRunnerSettings, asset::AssetPlugin, prelude::*};
+use bevy_carp::model::{Level, UndoBuffer};
+use bevy_pkv::PkvStore;
+use serde::Serialize;
+
+use crate::{
+    add_level_systems,
+    replay::{Replay, ReplayPlugin},
+    structs::{
+        ActionEvent, ActionLabel, ChangeBackground, CutChannel, GrabDropChannel, HammerChannel,
+        MenuChannel, MusicChannel, SwooshChannel, UndoChannel,
+    },
+    DonePlanks, PlankComponent,
+};
+use bevy_kira_audio::AudioChannel;
+
+// time to let animations finish after the last action
+const SETTLE_SECONDS: f64 = 2.0;
+
+#[derive(Serialize)]
+struct HeadlessReport {
+    won: bool,
+    holes: usize,
+    planks: usize,
+    done_planks: usize,
+    undo_position: usize,
+    undo_states: usize,
+}
+
+/// an app running the level systems under `MinimalPlugins`
+pub fn headless_app(settings: PkvStore) -> App {
+    let mut app = App::new();
+    app.insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(
+        1.0 / 60.0,
+    )))
+    .add_plugins(MinimalPlugins)
+    .add_plugin(TransformPlugin)
+    .add_plugin(HierarchyPlugin)
+    .add_plugin(AssetPlugin)
+    .add_asset::<Mesh>()
+    .add_asset::<Image>()
+    .add_asset::<StandardMaterial>()
+    .insert_resource(AudioChannel::<MusicChannel>::default())
+    .insert_resource(AudioChannel::<MenuChannel>::default())
+    .insert_resource(AudioChannel::<GrabDropChannel>::default())
+    .insert_resource(AudioChannel::<SwooshChannel>::default())
+    .insert_resource(AudioChannel::<HammerChannel>::default())
+    .insert_resource(AudioChannel::<CutChannel>::default())
+    .insert_resource(AudioChannel::<UndoChannel>::default())
+    .add_event::<ActionEvent>()
+    .add_event::<ChangeBackground>()
+    .add_plugin(ReplayPlugin);
+
+    add_level_systems(&mut app, settings);
+    app
+}
+
+// where tests keep their settings and saved replays, one per test run
+#[cfg(test)]
+pub fn scratch_dir() -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("bevy_carp test {}", std::process::id()))
+}
+
+pub fn run() {
+    // keep headless runs out of the player's settings
+    let mut app = headless_app(PkvStore::new("robtfm", "measure once headless"));
+    app.add_startup_system(start).add_system(finish);
+    app.run();
+}
+
+fn start(replay: Res<Replay>, mut actions: EventWriter<ActionEvent>) {
+    if replay.pending.is_none() {
+        eprintln!("usage: bevy_carp --headless --replay <file>");
+        std::process::exit(2);
+    }
+
+    actions.send(ActionEvent {
+        sender: Entity::from_raw(0),
+        label: ActionLabel("watch replay"),
+        target: None,
+    });
+}
+
+fn finish(
+    replay: Res<Replay>,
+    time: Res<Time>,
+    level: Res<Level>,
+    done_planks: Res<DonePlanks>,
+    undo: Res<UndoBuffer>,
+    planks: Query<(), With<PlankComponent>>,
+    mut started: Local<bool>,
+    mut finished_at: Local<Option<f64>>,
+) {
+    if replay.is_playing() {
+        *started = true;
+        return;
+    }
+    if !*started {
+        return;
+    }
+
+    let now = time.seconds_since_startup();
+    let finished_at = *finished_at.get_or_insert(now);
+    if now - finished_at < SETTLE_SECONDS {
+        return;
+    }
+
+    let report = HeadlessReport {
+        won: level.is_won(),
+        holes: level.holes.holes.len(),
+        planks: planks.iter().count(),
+        done_planks: done_planks.0.len(),
+        undo_position: undo.position(),
+        undo_states: undo.state_count(),
+    };
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    std::process::exit(match report.won {
+        true => 0,
+        false => 1,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use bevy::ecs::event::Events;
+    use bevy_carp::{
+        cut::Cut,
+        level_file::{HoleFile, LevelFile, Shape},
+        levels::LevelDef,
+        stats::LevelStats,
+    };
+
+    use super::*;
+    use crate::{
+        replay::{save_path, ReplayFile},
+        structs::Position,
+        Cursor, PositionOffset, Selected, SpawnLevelEvent, SystemController,
+    };
+
+    // settings and replays under the scratch dir, removed again when the test ends
+    struct Scratch;
+
+    impl Scratch {
+        fn new() -> Self {
+            let _ = std::fs::remove_dir_all(scratch_dir());
+            std::fs::create_dir_all(scratch_dir()).unwrap();
+            // the store lives under the data dir
+            std::env::set_var("XDG_DATA_HOME", scratch_dir());
+            Scratch
+        }
+
+        fn store(&self, name: &str) -> PkvStore {
+            PkvStore::new("robtfm", &format!("measure once test {}", name))
+        }
+    }
+
+    impl Drop for Scratch {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(scratch_dir());
+        }
+    }
+
+    fn grid(rows: &[&str]) -> Shape {
+        Shape::Grid(rows.iter().map(|row| row.to_string()).collect())
+    }
+
+    // a straight plank of three, a standing domino hole and a single cell hole
+    fn three() -> LevelDef {
+        LevelDef {
+            num_holes: 2,
+            total_blocks: 3,
+            seed: 0,
+            file: Some(LevelFile {
+                name: "Three".into(),
+                plank: grid(&["###"]),
+                holes: vec![
+                    HoleFile {
+                        at: [0, 0],
+                        shape: grid(&["#", "#"]),
+                    },
+                    HoleFile {
+                        at: [2, 0],
+                        shape: grid(&["#"]),
+                    },
+                ],
+                seed: 0,
+            }),
+        }
+    }
+
+    // movement is animated against the real clock, so frames are paced
+    fn frame(app: &mut App) {
+        std::thread::sleep(Duration::from_millis(2));
+        app.update();
+    }
+
+    // a gap between actions longer than a replay's frame timing can drift
+    fn pause(app: &mut App) {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(50) {
+            frame(app);
+        }
+    }
+
+    fn step(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            frame(app);
+        }
+    }
+
+    // run until the entities' transforms have caught up with their positions
+    fn settle(app: &mut App, entities: &[Entity]) {
+        let start = Instant::now();
+        loop {
+            frame(app);
+            let settled = entities.iter().all(|&ent| {
+                let entity = app.world.entity(ent);
+                let target = entity.get::<Position>().unwrap().0.as_vec2()
+                    + entity.get::<PositionOffset>().map_or(Vec2::ZERO, |o| o.0);
+                entity.get::<Transform>().unwrap().translation.truncate() == target
+            });
+            if settled {
+                break;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "never settled");
+        }
+        // slack for a replay's frames to land later than these did
+        pause(app);
+    }
+
+    fn send(app: &mut App, sender: Entity, label: &'static str) {
+        app.world
+            .resource_mut::<Events<ActionEvent>>()
+            .send(ActionEvent {
+                sender,
+                label: ActionLabel(label),
+                target: None,
+            });
+        pause(app);
+    }
+
+    fn single<C: Component>(app: &mut App) -> Entity {
+        let entities = app
+            .world
+            .query_filtered::<Entity, With<C>>()
+            .iter(&app.world)
+            .collect::<Vec<_>>();
+        assert_eq!(entities.len(), 1);
+        entities[0]
+    }
+
+    // step the senders a cell at a time, as held keys do
+    fn walk(app: &mut App, senders: &[Entity], by: IVec2) {
+        let moves = [
+            ("right", by.x.max(0)),
+            ("left", (-by.x).max(0)),
+            ("up", by.y.max(0)),
+            ("down", (-by.y).max(0)),
+        ];
+        for (label, count) in moves {
+            for _ in 0..count {
+                for &sender in senders {
+                    send(app, sender, label);
+                }
+                settle(app, senders);
+            }
+        }
+    }
+
+    // world cells of the loose planks, lowest first
+    fn planks(app: &mut App) -> Vec<(Entity, Vec<IVec2>)> {
+        app.world
+            .query::<(Entity, &PlankComponent, &Position)>()
+            .iter(&app.world)
+            .map(|(ent, plank, pos)| {
+                let mut cells = plank.0.coords.iter().map(|c| c + pos.0).collect::<Vec<_>>();
+                cells.sort_by_key(|c| (c.y, c.x));
+                (ent, cells)
+            })
+            .collect()
+    }
+
+    // world cells of the hole with `count` cells, lowest first
+    fn hole(app: &App, count: usize) -> Vec<IVec2> {
+        let level = app.world.resource::<Level>();
+        let hole = level
+            .holes
+            .holes
+            .iter()
+            .find(|h| h.count() == count)
+            .unwrap();
+        let mut cells = hole
+            .coords
+            .iter()
+            .map(|c| c + level.hole_origin())
+            .collect::<Vec<_>>();
+        cells.sort_by_key(|c| (c.y, c.x));
+        cells
+    }
+
+    // solve the level through actions alone, checking cut, undo, redo, turn and place on the way
+    fn solve_three(app: &mut App) {
+        app.world
+            .resource_mut::<Events<SpawnLevelEvent>>()
+            .send(SpawnLevelEvent { def: three() });
+        step(app, 5);
+        let cursor = single::<Cursor>(app);
+        settle(app, &[cursor]);
+
+        let undo_start = app.world.resource::<UndoBuffer>().state_count();
+        let [(_, cells)]: [_; 1] = planks(app).try_into().ok().unwrap();
+
+        // cut along the line before the last cell, starting from its bottom left corner
+        let at = app.world.get::<Position>(cursor).unwrap().0;
+        walk(app, &[cursor], cells[2] - at);
+
+        // a finished cut that is cancelled doesn't count
+        send(app, cursor, "cut");
+        let cutter = single::<Cut>(app);
+        send(app, cutter, "up");
+        send(app, cutter, "cancel");
+        assert_eq!(planks(app).len(), 1);
+        assert_eq!(app.world.resource::<LevelStats>().cuts, 0);
+
+        // the next cut starts from the corner the last one ended at
+        send(app, cursor, "cut");
+        let cutter = single::<Cut>(app);
+        send(app, cutter, "down");
+        assert!(app.world.get::<Cut>(cutter).unwrap().finished);
+        send(app, cutter, "finish cut");
+
+        let mut sizes = planks(app)
+            .iter()
+            .map(|(_, cells)| cells.len())
+            .collect::<Vec<_>>();
+        sizes.sort();
+        assert_eq!(sizes, [1, 2]);
+        assert!(app.world.resource::<UndoBuffer>().state_count() > undo_start);
+
+        // undo puts the plank back together, redo cuts it again
+        let system = single::<SystemController>(app);
+        send(app, system, "undo");
+        assert_eq!(planks(app).len(), 1);
+        let system = single::<SystemController>(app);
+        send(app, system, "redo");
+        assert_eq!(planks(app).len(), 2);
+
+        // grab each piece, turn the domino upright, carry it over its hole and drop it in
+        while let Some((plank, cells)) = planks(app).pop() {
+            // undo and redo respawn the cursor
+            let cursor = single::<Cursor>(app);
+            let at = app.world.get::<Position>(cursor).unwrap().0;
+            walk(app, &[cursor], cells[0] - at);
+            send(app, cursor, "grab");
+            assert_eq!(single::<Selected>(app), plank);
+
+            let hole = hole(app, cells.len());
+            if cells.len() == 2 {
+                send(app, plank, "rotate left");
+                settle(app, &[plank]);
+            }
+            let [(_, cells)]: [_; 1] = planks(app)
+                .into_iter()
+                .filter(|(ent, _)| *ent == plank)
+                .collect::<Vec<_>>()
+                .try_into()
+                .ok()
+                .unwrap();
+            walk(app, &[cursor, plank], hole[0] - cells[0]);
+            send(app, cursor, "drop");
+        }
+
+        assert!(app.world.resource::<Level>().is_won());
+        assert_eq!(app.world.resource::<DonePlanks>().0.len(), 2);
+        let undo = app.world.resource::<UndoBuffer>();
+        assert_eq!(undo.position(), undo.state_count() - 1);
+        let stats = app.world.resource::<LevelStats>();
+        assert_eq!((stats.cuts, stats.par, stats.undos), (1, 1, 1));
+    }
+
+    #[test]
+    fn solve_and_replay() {
+        let scratch = Scratch::new();
+
+        let mut app = headless_app(scratch.store("record"));
+        solve_three(&mut app);
+        step(&mut app, 3);
+        drop(app);
+
+        // the finished level was saved, play it back in a fresh app
+        let file: ReplayFile =
+            serde_json::from_slice(&std::fs::read(save_path()).unwrap()).unwrap();
+        let mut app = headless_app(scratch.store("play"));
+        app.world.resource_mut::<Replay>().pending = Some(file);
+        send(&mut app, Entity::from_raw(0), "watch replay");
+
+        let start = Instant::now();
+        while app.world.resource::<Replay>().is_playing() {
+            assert!(
+                start.elapsed() < Duration::from_secs(30),
+                "replay never finished"
+            );
+            frame(&mut app);
+        }
+        step(&mut app, 30);
+        assert!(app.world.resource::<Level>().is_won());
+    }
+}
@@ -0,0 +1,345 @@
+// show the next step of a solution from the current state
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{prelude::*, tasks::AsyncComputeTaskPool};
+use bevy_carp::model::{solve_within, CoordSet, GaveUp, Level, Solution};
+use bevy_egui::{egui, EguiContext};
+
+use crate::{
+    bl_quad::BLQuad,
+    menus::{PopupMenu, PopupMenuEvent},
+    structs::{ActionEvent, ActionLabel, Position},
+    CutEvent, MHoles, PlankComponent, Selected, Targeted, UndoBuffer, PLANK_Z_SELECTED,
+};
+
+const HINT_DURATION: f64 = 4.0;
+// placements to try before giving up, a few hundred ms on big levels
+const HINT_STEPS: usize = 200_000;
+
+pub struct HintPlugin;
+
+impl Plugin for HintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingHint>()
+            .add_system(show_hint)
+            .add_system(clear_hints.after(show_hint))
+            .add_system(show_pending);
+    }
+}
+
+#[derive(Component, Clone)]
+struct HintGhost {
+    until: f64,
+}
+
+/// an unlit see through material for marking cells and edges over the level
+pub fn overlay_material(
+    materials: &mut Assets<StandardMaterial>,
+    color: Color,
+) -> Handle<StandardMaterial> {
+    materials.add(StandardMaterial {
+        base_color: color,
+        unlit: true,
+        alpha_mode: AlphaMode::Blend,
+        ..Default::default()
+    })
+}
+
+/// segment meshes and a colour for drawing outlines
+pub struct OutlineAssets {
+    horizontal: Handle<Mesh>,
+    vertical: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+impl OutlineAssets {
+    pub fn new(
+        meshes: &mut Assets<Mesh>,
+        materials: &mut Assets<StandardMaterial>,
+        color: Color,
+    ) -> Self {
+        Self {
+            horizontal: meshes.add(BLQuad::new(Vec2::new(1.2, 0.2), Vec2::ZERO).into()),
+            vertical: meshes.add(BLQuad::new(Vec2::new(0.2, 1.2), Vec2::ZERO).into()),
+            material: overlay_material(materials, color),
+        }
+    }
+}
+
+/// a bar along each unit segment, just above the planks, tagged with `marker`
+pub fn spawn_outline(
+    commands: &mut Commands,
+    assets: &OutlineAssets,
+    segments: impl IntoIterator<Item = (IVec2, IVec2)>,
+    marker: impl Component + Clone,
+) {
+    for (from, to) in segments {
+        let mesh = match from.y == to.y {
+            true => assets.horizontal.clone(),
+            false => assets.vertical.clone(),
+        };
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh,
+                material: assets.material.clone(),
+                transform: Transform::from_translation(
+                    (from.min(to).as_vec2() - 0.1).extend(PLANK_Z_SELECTED + 0.01),
+                ),
+                ..Default::default()
+            })
+            .insert(marker.clone());
+    }
+}
+
+// unit segments around the outside of a set of cells
+pub fn outline(cells: &CoordSet, offset: IVec2) -> Vec<(IVec2, IVec2)> {
+    let mut segments = Vec::new();
+    for coord in cells.coords.iter() {
+        let cell = coord + offset;
+        if !cells.contains(coord - IVec2::Y) {
+            segments.push((cell, cell + IVec2::X));
+        }
+        if !cells.contains(coord + IVec2::Y) {
+            segments.push((cell + IVec2::Y, cell + IVec2::ONE));
+        }
+        if !cells.contains(coord - IVec2::X) {
+            segments.push((cell, cell + IVec2::Y));
+        }
+        if !cells.contains(coord + IVec2::X) {
+            segments.push((cell + IVec2::X, cell + IVec2::ONE));
+        }
+    }
+    segments
+}
+
+type HintResult = Result<Option<Solution>, GaveUp>;
+
+// a hint being solved off the main thread
+struct HintTask {
+    sender: Entity,
+    level: Level,
+    focus: Option<usize>,
+    result: Arc<Mutex<Option<HintResult>>>,
+}
+
+impl HintTask {
+    fn spawn(
+        pool: &AsyncComputeTaskPool,
+        sender: Entity,
+        level: Level,
+        focus: Option<usize>,
+    ) -> Self {
+        let result = Arc::new(Mutex::new(None));
+        let to_solve = level.clone();
+        let slot = result.clone();
+        pool.spawn(async move {
+            *slot.lock().unwrap() = Some(solve_within(&to_solve, HINT_STEPS));
+        })
+        .detach();
+
+        Self {
+            sender,
+            level,
+            focus,
+            result,
+        }
+    }
+}
+
+#[derive(Default)]
+struct PendingHint(Option<HintTask>);
+
+fn show_hint(
+    mut commands: Commands,
+    mut evs: EventReader<ActionEvent>,
+    level: Res<Level>,
+    undo: Res<UndoBuffer>,
+    planks: Query<(
+        &PlankComponent,
+        &Position,
+        Option<&Selected>,
+        Option<&Targeted>,
+    )>,
+    holes: Query<&Position, With<MHoles>>,
+    mut ghosts: Query<&mut HintGhost>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: Local<Option<OutlineAssets>>,
+    mut pending: ResMut<PendingHint>,
+    mut menu: EventWriter<PopupMenuEvent>,
+    pool: Res<AsyncComputeTaskPool>,
+    time: Res<Time>,
+) {
+    // the live planks replace the level's planks, holes are removed as they are filled
+    let live = || {
+        let mut current = level.clone();
+        current.planks.clear();
+        let mut focus = None;
+        for (plank, pos, selected, targeted) in planks.iter() {
+            if selected.is_some() || (targeted.is_some() && focus.is_none()) {
+                focus = Some(current.planks.len());
+            }
+            current.planks.push((plank.0.clone(), *pos));
+        }
+        (current, focus)
+    };
+
+    for ev in evs.iter() {
+        if ev.label.0 != "hint" {
+            continue;
+        }
+
+        // expire any previous hint
+        for mut ghost in ghosts.iter_mut() {
+            ghost.until = 0.0;
+        }
+
+        let (current, focus) = live();
+        if current.planks.is_empty() {
+            continue;
+        }
+
+        pending.0 = Some(HintTask::spawn(&pool, ev.sender, current, focus));
+    }
+
+    let Some(hint) = pending.0.as_ref() else {
+        return;
+    };
+    let Some(result) = hint.result.lock().unwrap().take() else {
+        return;
+    };
+    let HintTask {
+        sender,
+        level: current,
+        focus,
+        ..
+    } = pending.0.take().unwrap();
+
+    // the planks moved on while solving, solve again from where they are now
+    let (live_level, live_focus) = live();
+    if current.planks != live_level.planks {
+        debug!("hint: stale, solving again");
+        if !live_level.planks.is_empty() {
+            pending.0 = Some(HintTask::spawn(&pool, sender, live_level, live_focus));
+        }
+        return;
+    }
+
+    let Ok(hole_pos) = holes.get_single() else {
+        return;
+    };
+
+    let solution = match result {
+        Ok(Some(solution)) => solution,
+        Ok(None) => {
+            debug!("hint: no solution");
+            menu.send(PopupMenuEvent {
+                sender,
+                menu: PopupMenu {
+                    heading: "No solution from here".into(),
+                    items: vec![
+                        ("Undo".into(), ActionLabel("undo"), undo.has_back()),
+                        ("Restart Level".into(), ActionLabel("restart"), true),
+                        ("Resume".into(), ActionLabel("cancel"), true),
+                    ],
+                    cancel_action: Some(ActionLabel("cancel")),
+                    ..Default::default()
+                },
+                sound: false,
+            });
+            return;
+        }
+        Err(GaveUp) => {
+            debug!("hint: gave up");
+            menu.send(PopupMenuEvent {
+                sender,
+                menu: PopupMenu {
+                    heading: "No hint for this one".into(),
+                    items: vec![("Resume".into(), ActionLabel("cancel"), true)],
+                    cancel_action: Some(ActionLabel("cancel")),
+                    ..Default::default()
+                },
+                sound: false,
+            });
+            return;
+        }
+    };
+
+    let is_whole = |plank: usize| solution.pieces.iter().filter(|p| p.plank == plank).count() == 1;
+
+    // hint for the plank in hand, else prefer placing a finished piece over cutting
+    let plank = focus
+        .or_else(|| (0..current.planks.len()).find(|&plank| is_whole(plank)))
+        .unwrap_or(0);
+    let (plank_coords, plank_pos) = &current.planks[plank];
+
+    let segments = if is_whole(plank) {
+        let piece = solution.pieces.iter().find(|p| p.plank == plank).unwrap();
+        debug!(
+            "hint: plank {} -> hole {} ({} turns)",
+            plank, piece.hole, piece.turns
+        );
+        let mut segments = outline(plank_coords, plank_pos.0);
+        segments.extend(outline(&current.holes.holes[piece.hole], hole_pos.0));
+        segments
+    } else {
+        let Some(cut) = solution.cuts.iter().find(|c| c.plank == plank) else {
+            return;
+        };
+        debug!("hint: cut plank {}: {:?}", plank, cut.separated);
+        // same edge model as `extend_cut`: a vertical pair is split by the horizontal segment
+        // along the top cell's bottom edge, a horizontal pair by the right cell's left edge
+        cut.separated
+            .iter()
+            .map(|(a, b)| {
+                let from = *b + plank_pos.0;
+                match *b - *a == IVec2::Y {
+                    true => (from, from + IVec2::X),
+                    false => (from, from + IVec2::Y),
+                }
+            })
+            .collect()
+    };
+
+    let assets = assets.get_or_insert_with(|| {
+        OutlineAssets::new(&mut meshes, &mut materials, Color::rgba(0.6, 0.9, 1.0, 0.6))
+    });
+    let until = time.seconds_since_startup() + HINT_DURATION;
+    spawn_outline(&mut commands, assets, segments, HintGhost { until });
+}
+
+// let the player know a hint is on its way
+fn show_pending(mut egui_context: ResMut<EguiContext>, pending: Res<PendingHint>) {
+    if pending.0.is_none() {
+        return;
+    }
+
+    egui::Window::new("hint")
+        .anchor(egui::Align2::CENTER_BOTTOM, (0.0, -5.0))
+        .title_bar(false)
+        .resizable(false)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.label("Finding a hint...");
+        });
+}
+
+fn clear_hints(
+    mut commands: Commands,
+    ghosts: Query<(Entity, &HintGhost)>,
+    mut cut_evs: EventReader<CutEvent>,
+    time: Res<Time>,
+) {
+    // a finished or abandoned cut makes the hint stale
+    let mut stale = false;
+    for ev in cut_evs.iter() {
+        stale |= matches!(ev, CutEvent::FinishCut | CutEvent::CancelCut);
+    }
+    let now = time.seconds_since_startup();
+
+    for (ent, ghost) in ghosts.iter() {
+        if stale || now > ghost.until {
+            commands.entity(ent).despawn_recursive();
+        }
+    }
+}
@@ -4,14 +4,16 @@ use std::marker::PhantomData;
 
 use bevy::{
     ecs::system::SystemParam,
+    input::mouse::MouseWheel,
     prelude::*,
     utils::{HashMap, HashSet},
 };
 
-use bevy_egui::{egui, EguiContext};
+use bevy_egui::{egui, EguiContext, EguiSystem};
 use egui_extras::StripBuilder;
 
 use crate::{
+    replay::Replaying,
     structs::{ActionEvent, ActionLabel, ControlHelp, LevelDef},
     LevelSet,
 };
@@ -23,6 +25,8 @@ impl Plugin for InputPlugin {
         app.init_resource::<GamePadRes>()
             .init_resource::<ActionInputs>()
             .init_resource::<LastControlType>()
+            .init_resource::<MouseInputs>()
+            .init_resource::<StickResponse>()
             .add_event::<ActionEvent>()
             .add_event::<NewInputEvent>()
             // init
@@ -32,14 +36,36 @@ impl Plugin for InputPlugin {
             .add_system(show_status)
             // input
             .add_system(pad_connection)
+            .add_system(save_stick_response)
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                read_mouse
+                    .after(EguiSystem::BeginFrame)
+                    .before(controller)
+                    .before(new_input_controller),
+            )
             .add_system_to_stage(CoreStage::PreUpdate, controller)
             .add_system_to_stage(CoreStage::PreUpdate, new_input_controller);
     }
 }
 
-fn init_settings(mut settings: ResMut<PkvStore>, mut inputs: ResMut<ActionInputs>) {
-    match settings.get("inputs") {
-        Ok(set_inputs) => *inputs = set_inputs,
+fn init_settings(
+    mut settings: ResMut<PkvStore>,
+    mut inputs: ResMut<ActionInputs>,
+    mut stick: ResMut<StickResponse>,
+) {
+    if let Ok(stored) = settings.get::<StickResponse>("stick response") {
+        *stick = stored;
+    }
+
+    match settings.get::<ActionInputs>("inputs") {
+        Ok(mut set_inputs) => {
+            // pick up defaults for actions added since the settings were stored
+            for (ty, items) in ActionInputs::default().items.into_iter() {
+                set_inputs.items.entry(ty).or_insert(items);
+            }
+            *inputs = set_inputs;
+        }
         Err(_) => settings.set("inputs", &ActionInputs::default()).unwrap(),
     }
 }
@@ -47,6 +73,49 @@ fn init_settings(mut settings: ResMut<PkvStore>, mut inputs: ResMut<ActionInputs
 #[derive(Default)]
 pub struct GamePadRes(pub Option<Gamepad>);
 
+/// how the sticks respond. each axis ignores deflection inside the dead zone, and the rest is raised to the
+/// curve so a light push moves the cursor slowly
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct StickResponse {
+    pub dead_zone: f32,
+    pub curve: f32,
+}
+
+impl Default for StickResponse {
+    fn default() -> Self {
+        Self {
+            dead_zone: 0.2,
+            curve: 2.0,
+        }
+    }
+}
+
+impl StickResponse {
+    /// how hard an axis is pushed, from 0 at the dead zone to 1 at the rim
+    pub fn strength(&self, value: f32) -> f32 {
+        ((value.abs() - self.dead_zone) / (1.0 - self.dead_zone))
+            .clamp(0.0, 1.0)
+            .powf(self.curve)
+    }
+}
+
+fn save_stick_response(stick: Res<StickResponse>, mut settings: ResMut<PkvStore>) {
+    if stick.is_changed() {
+        settings.set("stick response", &*stick).unwrap();
+    }
+}
+
+/// scales how fast an entity's transform follows its position on each axis, from how far the stick moving it
+/// is pushed
+#[derive(Component)]
+pub struct AnalogPace(pub Vec2);
+
+impl Default for AnalogPace {
+    fn default() -> Self {
+        Self(Vec2::ONE)
+    }
+}
+
 fn pad_connection(mut pad: ResMut<GamePadRes>, mut gamepad_event: EventReader<GamepadEvent>) {
     for event in gamepad_event.iter() {
         match &event {
@@ -79,6 +148,23 @@ fn pad_connection(mut pad: ResMut<GamePadRes>, mut gamepad_event: EventReader<Ga
     }
 }
 
+/// the wheel this frame, and whether the pointer is over a menu or panel
+#[derive(Default)]
+pub struct MouseInputs {
+    pub scroll: f32,
+    pub over_ui: bool,
+}
+
+pub fn read_mouse(
+    mut mouse: ResMut<MouseInputs>,
+    mut wheel: EventReader<MouseWheel>,
+    mut egui_context: ResMut<EguiContext>,
+) {
+    mouse.scroll = wheel.iter().map(|ev| ev.y).sum();
+    let ctx = egui_context.ctx_mut();
+    mouse.over_ui = ctx.is_pointer_over_area() || ctx.wants_pointer_input();
+}
+
 #[derive(Clone, Copy)]
 pub enum DisplayMode {
     Off,
@@ -114,6 +200,7 @@ pub enum ActionType {
     ZoomOut,
     TurnLeft,
     TurnRight,
+    Hint,
 }
 
 impl ActionType {
@@ -137,6 +224,7 @@ impl ActionType {
             ActionType::ZoomOut => "zoom out",
             ActionType::TurnLeft => "rotate left",
             ActionType::TurnRight => "rotate right",
+            ActionType::Hint => "hint",
         }
     }
 }
@@ -162,9 +250,12 @@ pub struct Controller {
 #[derive(SystemParam)]
 pub struct InputParams<'w, 's> {
     key_input: Res<'w, Input<KeyCode>>,
+    mouse_buttons: Res<'w, Input<MouseButton>>,
+    mouse: Res<'w, MouseInputs>,
     pad: Res<'w, GamePadRes>,
     axes: Res<'w, Axis<GamepadAxis>>,
     buttons: Res<'w, Axis<GamepadButton>>,
+    stick: Res<'w, StickResponse>,
 
     #[system_param(ignore)]
     _marker: PhantomData<(&'w (), &'s ())>,
@@ -246,6 +337,17 @@ fn new_input_controller(
             new_inputs.send(NewInputEvent(ent, InputItem::Key(*k)))
         }
 
+        for b in inputs.mouse_buttons.get_just_pressed() {
+            new_inputs.send(NewInputEvent(ent, InputItem::Mouse(*b)))
+        }
+
+        if inputs.mouse.scroll != 0.0 {
+            new_inputs.send(NewInputEvent(
+                ent,
+                InputItem::Wheel(inputs.mouse.scroll > 0.0),
+            ));
+        }
+
         if let Some(pad) = inputs.pad.0 {
             for button_type in ALL_BUTTONS.iter() {
                 if inputs
@@ -286,18 +388,27 @@ fn new_input_controller(
 
 fn controller(
     inputs: InputParams,
-    mut controllers: Query<(Entity, &mut Controller)>,
+    mut controllers: Query<(
+        Entity,
+        &mut Controller,
+        Option<&Replaying>,
+        Option<&mut AnalogPace>,
+    )>,
     mut actions: EventWriter<ActionEvent>,
     mut mapping: ResMut<ActionInputs>,
     mut last_used: ResMut<LastControlType>,
 ) {
-    for (ent, mut options) in controllers.iter_mut() {
+    for (ent, mut options, replaying, pace) in controllers.iter_mut() {
         if !options.enabled {
             options.initialized = false;
             continue;
         }
 
         for &(trigger, action) in options.actions.iter() {
+            // a replay drives the level, but it can still be paused
+            if replaying.is_some() && trigger != ActionType::Menu {
+                continue;
+            }
             if mapping.active(trigger, action.sticky, &inputs) && options.initialized {
                 actions.send(ActionEvent {
                     sender: ent,
@@ -307,6 +418,17 @@ fn controller(
             }
         }
 
+        // a stick moves at its deflection, keys and everything else at full speed
+        if let Some(mut pace) = pace {
+            pace.0 = match replaying {
+                Some(_) => Vec2::ONE,
+                None => Vec2::new(
+                    mapping.pace(ActionType::MoveLeft, ActionType::MoveRight),
+                    mapping.pace(ActionType::MoveDown, ActionType::MoveUp),
+                ),
+            };
+        }
+
         options.initialized = true;
         *last_used = mapping.last_used;
     }
@@ -324,6 +446,9 @@ pub enum InputItem {
     Key(KeyCode),
     Axis(GamepadAxisType, bool),
     Button(GamepadButtonType),
+    Mouse(MouseButton),
+    // up
+    Wheel(bool),
 }
 
 fn key_text(k: &KeyCode) -> String {
@@ -475,11 +600,77 @@ impl InputItem {
             }
         }
 
+        // a mouse with one button filled in, or an arrow for the wheel
+        fn draw_mouse(
+            button: Option<MouseButton>,
+            wheel: Option<bool>,
+            color: egui::Color32,
+            ui: &mut egui::Ui,
+        ) {
+            let (response, painter) =
+                ui.allocate_painter(egui::vec2(35.0, 32.0), egui::Sense::hover());
+            let rect = egui::Rect::from_center_size(response.rect.center(), egui::vec2(18.0, 28.0));
+            let stroke = egui::Stroke::new(1.0, color);
+            let mid = rect.center().x;
+            let split = rect.top() + 11.0;
+
+            painter.rect_stroke(rect, egui::Rounding::same(7.0), stroke);
+            painter.line_segment(
+                [
+                    egui::pos2(rect.left(), split),
+                    egui::pos2(rect.right(), split),
+                ],
+                stroke,
+            );
+            painter.line_segment(
+                [egui::pos2(mid, rect.top()), egui::pos2(mid, split)],
+                stroke,
+            );
+
+            let filled = match button {
+                Some(MouseButton::Left) => Some(egui::Rect::from_min_max(
+                    egui::pos2(rect.left() + 2.0, rect.top() + 3.0),
+                    egui::pos2(mid - 1.0, split - 1.0),
+                )),
+                Some(MouseButton::Right) => Some(egui::Rect::from_min_max(
+                    egui::pos2(mid + 1.0, rect.top() + 3.0),
+                    egui::pos2(rect.right() - 2.0, split - 1.0),
+                )),
+                Some(MouseButton::Middle) => Some(egui::Rect::from_center_size(
+                    egui::pos2(mid, rect.top() + 6.0),
+                    egui::vec2(3.0, 6.0),
+                )),
+                _ => None,
+            };
+            if let Some(filled) = filled {
+                painter.rect_filled(filled, egui::Rounding::same(2.0), color);
+            }
+
+            match wheel {
+                Some(true) => draw_arrow(
+                    &painter,
+                    egui::pos2(mid, split + 5.0),
+                    egui::vec2(0.0, 3.0),
+                    color,
+                ),
+                Some(false) => draw_arrow(
+                    &painter,
+                    egui::pos2(mid, rect.bottom() - 5.0),
+                    egui::vec2(0.0, -3.0),
+                    color,
+                ),
+                None => (),
+            }
+        }
+
         match self {
             InputItem::Key(k) => {
                 let text = key_text(k);
                 draw_key(text, ui, color);
             }
+            InputItem::Mouse(MouseButton::Other(b)) => draw_key(format!("M{}", b), ui, color),
+            InputItem::Mouse(b) => draw_mouse(Some(*b), None, color, ui),
+            InputItem::Wheel(up) => draw_mouse(None, Some(*up), color, ui),
             InputItem::Axis(x, right) => {
                 let (text, horiz) = match x {
                     GamepadAxisType::LeftStickX => ("L", true),
@@ -576,6 +767,9 @@ pub struct ActionInputs {
     prev: HashSet<ActionType>,
     #[serde(skip)]
     last_used: LastControlType,
+    // the strength of actions held by a stick when last checked
+    #[serde(skip)]
+    strength: HashMap<ActionType, f32>,
 }
 
 impl Default for ActionInputs {
@@ -691,20 +885,30 @@ impl Default for ActionInputs {
                     TurnRight,
                     vec![Key(KeyCode::E), Button(GamepadButtonType::RightTrigger)],
                 ),
+                (
+                    Hint,
+                    vec![Key(KeyCode::H), Button(GamepadButtonType::Select)],
+                ),
             ]),
             prev: Default::default(),
             last_used: Default::default(),
+            strength: Default::default(),
         }
     }
 }
 
 impl ActionInputs {
+    /// whether any action is bound to `item`
+    pub fn is_bound(&self, item: &InputItem) -> bool {
+        self.items.values().any(|items| items.contains(item))
+    }
+
     pub fn active(&mut self, action: ActionType, sticky: bool, inputs: &InputParams) -> bool {
         if !sticky {
-            return self.check_active(action, inputs);
+            return self.check_active(action, sticky, inputs);
         }
 
-        let is_active = self.check_active(action, inputs);
+        let is_active = self.check_active(action, sticky, inputs);
         if is_active {
             if !self.prev.contains(&action) {
                 self.prev.insert(action);
@@ -717,10 +921,24 @@ impl ActionInputs {
         }
     }
 
-    fn check_active(&mut self, action: ActionType, inputs: &InputParams) -> bool {
+    /// how fast to move along an axis: the strength of whichever direction a stick is holding, or full speed
+    pub fn pace(&self, negative: ActionType, positive: ActionType) -> f32 {
+        [negative, positive]
+            .iter()
+            .find_map(|action| self.strength.get(action).copied())
+            .unwrap_or(1.0)
+    }
+
+    fn check_active(&mut self, action: ActionType, sticky: bool, inputs: &InputParams) -> bool {
+        self.strength.remove(&action);
         let Some(items) = self.items.get(&action) else {
             return false;
         };
+        // one-shot actions need a firm push so menus don't skip on a drifting stick
+        let threshold = match sticky {
+            true => f32::max(0.5, inputs.stick.dead_zone),
+            false => inputs.stick.dead_zone,
+        };
 
         for item in items.iter() {
             match item {
@@ -741,16 +959,26 @@ impl ActionInputs {
                             })  */
                             .get(GamepadAxis(gamepad, *axis_type))
                             .unwrap();
-                        if axis > 0.5 && *right {
-                            self.last_used = LastControlType::Gamepad;
-                            return true;
-                        }
-                        if axis < -0.5 && !*right {
+                        if axis.abs() > threshold && (axis > 0.0) == *right {
                             self.last_used = LastControlType::Gamepad;
+                            self.strength.insert(action, inputs.stick.strength(axis));
                             return true;
                         }
                     }
                 }
+                InputItem::Mouse(button) => {
+                    if !inputs.mouse.over_ui && inputs.mouse_buttons.pressed(*button) {
+                        self.last_used = LastControlType::Keyboard;
+                        return true;
+                    }
+                }
+                InputItem::Wheel(up) => {
+                    let scroll = inputs.mouse.scroll;
+                    if !inputs.mouse.over_ui && scroll != 0.0 && (scroll > 0.0) == *up {
+                        self.last_used = LastControlType::Keyboard;
+                        return true;
+                    }
+                }
                 #[allow(unused_mut)]
                 InputItem::Button(mut button_type) => {
                     // fix webgl button mapping, for me at least
@@ -797,7 +1025,12 @@ fn show_action(
 ) {
     if let Some(input) = actions.items.get(&item).and_then(|v| {
         v.iter()
-            .find(|i| matches!(i, InputItem::Key(_)) == prefer_keyboard)
+            .find(|i| {
+                matches!(
+                    i,
+                    InputItem::Key(_) | InputItem::Mouse(_) | InputItem::Wheel(_)
+                ) == prefer_keyboard
+            })
             .or(v.iter().next())
     }) {
         ui.horizontal(|ui| {
@@ -932,7 +1165,7 @@ fn show_status(mut egui_context: ResMut<EguiContext>, set: Res<LevelSet>, def: R
             .show(egui_context.ctx_mut(), |ui| {
                 ui.set_max_width(100.0);
                 ui.label(&set.title);
-                ui.label(format!("{}/{}", set.current_level + 1, 30));
+                ui.label(format!("{}/{}", set.current_level + 1, set.levels.len()));
             });
     }
 }
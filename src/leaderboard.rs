@@ -0,0 +1,255 @@
+// local top results per level, for taking turns on one machine. results are entered under the player name,
+// which is picked a letter at a time so it works with a controller.
+
+use bevy::prelude::*;
+use bevy_carp::{
+    levels::{LevelDef, LevelSet},
+    stats::{Leaderboard, LeaderboardEntry, LevelStats},
+};
+use bevy_egui::egui;
+use bevy_pkv::PkvStore;
+
+use crate::{
+    editor::EDITOR_KEY,
+    menus::{EntityMarker, MenuItem, PopupMenu, PopupMenuEvent},
+    replay::REPLAY_KEY,
+    structs::{ActionEvent, ActionLabel},
+};
+
+const NAME_KEY: &str = "player name";
+const DEFAULT_NAME: &str = "Player";
+const NAME_CHARS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+const MAX_NAME_LEN: usize = 12;
+
+pub struct LeaderboardPlugin;
+
+impl Plugin for LeaderboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_leaderboard)
+            .add_system(spawn_name_editor);
+    }
+}
+
+pub fn player_name(settings: &PkvStore) -> String {
+    settings
+        .get(NAME_KEY)
+        .unwrap_or_else(|_| DEFAULT_NAME.to_string())
+}
+
+fn board_key(set: &LevelSet, def: &LevelDef) -> Option<String> {
+    if set.settings_key == EDITOR_KEY || set.settings_key == REPLAY_KEY {
+        return None;
+    }
+    Some(Leaderboard::key(&set.settings_key, def))
+}
+
+fn board(settings: &PkvStore, set: &LevelSet, def: &LevelDef) -> Leaderboard {
+    board_key(set, def)
+        .and_then(|key| settings.get(&key).ok())
+        .unwrap_or_default()
+}
+
+/// enter a finished level under the player's name, returning the rank if it made the table
+pub fn record(
+    settings: &mut PkvStore,
+    set: &LevelSet,
+    def: &LevelDef,
+    stats: &LevelStats,
+) -> Option<usize> {
+    let key = board_key(set, def)?;
+    let mut board: Leaderboard = settings.get(&key).unwrap_or_default();
+    let rank = board.insert(LeaderboardEntry {
+        name: player_name(settings),
+        stats: *stats,
+    })?;
+    settings.set(&key, &board).unwrap();
+    Some(rank)
+}
+
+// a row per entry: rank, name, cuts, undos, time
+fn table_items(board: &Leaderboard) -> Vec<(MenuItem, ActionLabel, bool)> {
+    let mut items: Vec<(MenuItem, ActionLabel, bool)> = ["", "Name", "Cuts", "Undos", "Time"]
+        .into_iter()
+        .map(|text| (text.into(), ActionLabel(""), false))
+        .collect();
+    for (i, entry) in board.entries.iter().enumerate() {
+        for text in [
+            format!("{}.", i + 1),
+            entry.name.clone(),
+            entry.stats.cuts.to_string(),
+            entry.stats.undos.to_string(),
+            format!("{:.1}s", entry.stats.time),
+        ] {
+            items.push((text.into(), ActionLabel(""), false));
+        }
+    }
+    if board.entries.is_empty() {
+        items.push(("No results yet".into(), ActionLabel(""), false));
+    }
+    // finish the row so the buttons start on their own
+    while !items.len().is_multiple_of(5) {
+        items.push(("".into(), ActionLabel(""), false));
+    }
+    items
+}
+
+fn spawn_leaderboard(
+    mut evs: EventReader<ActionEvent>,
+    mut spawn: EventWriter<PopupMenuEvent>,
+    settings: Res<PkvStore>,
+    set: Res<LevelSet>,
+    def: Res<LevelDef>,
+    mut level: Local<usize>,
+) {
+    for ev in evs.iter() {
+        let from_select = match ev.label.0 {
+            // after finishing the current level
+            "leaderboard" => false,
+            // flicking through the set from level select
+            "level scores" => {
+                *level = set.current_level;
+                true
+            }
+            "prev scores" => {
+                *level = level.saturating_sub(1);
+                true
+            }
+            "next scores" => {
+                *level = (*level + 1).min(set.levels.len().saturating_sub(1));
+                true
+            }
+            _ => continue,
+        };
+
+        let (index, level_def) = match from_select {
+            true => {
+                let Some(level_def) = set.levels.get(*level) else {
+                    continue;
+                };
+                (*level, level_def)
+            }
+            false => (set.current_level, &*def),
+        };
+
+        let mut items = table_items(&board(&settings, &set, level_def));
+        if from_select {
+            items.extend([
+                ("<".into(), ActionLabel("prev scores"), index > 0),
+                ("Back".into(), ActionLabel("play current set"), true),
+                (
+                    ">".into(),
+                    ActionLabel("next scores"),
+                    index + 1 < set.levels.len(),
+                ),
+            ]);
+        } else {
+            items.extend([
+                (
+                    "Next Level".into(),
+                    ActionLabel("next level"),
+                    index + 1 < set.levels.len(),
+                ),
+                ("Restart Level".into(), ActionLabel("restart"), true),
+                ("Main Menu".into(), ActionLabel("main menu"), true),
+            ]);
+        }
+
+        spawn.send(PopupMenuEvent {
+            sender: ev.sender,
+            menu: PopupMenu {
+                heading: format!("{}\nLevel {} Leaderboard", set.title, index + 1),
+                items,
+                cancel_action: Some(match from_select {
+                    true => ActionLabel("play current set"),
+                    false => ActionLabel("main menu"),
+                }),
+                width: 5,
+                initial_position: -1,
+                inactive_color: egui::Color32::from_rgb(255, 255, 255),
+                text_size: 25.0,
+                ..Default::default()
+            },
+            sound: false,
+        });
+    }
+}
+
+fn spawn_name_editor(
+    mut commands: Commands,
+    mut evs: EventReader<ActionEvent>,
+    mut actions: EventWriter<ActionEvent>,
+    mut spawn: EventWriter<PopupMenuEvent>,
+    mut settings: ResMut<PkvStore>,
+    mut sender: Local<EntityMarker>,
+    mut name: Local<Option<String>>,
+) {
+    if matches!(*sender, EntityMarker::None) {
+        *sender = commands.spawn().id().into();
+    }
+
+    for ev in evs.iter() {
+        match ev.label.0 {
+            "edit name" => *name = Some(player_name(&settings)),
+            _ if !sender.matches(&ev.sender) => continue,
+            "name space" => name.get_or_insert_with(String::new).push(' '),
+            "name delete" => {
+                name.get_or_insert_with(String::new).pop();
+            }
+            "name done" => {
+                let entered = name.take().unwrap_or_default();
+                let entered = match entered.trim() {
+                    "" => DEFAULT_NAME,
+                    trimmed => trimmed,
+                };
+                settings.set(NAME_KEY, &entered.to_string()).unwrap();
+                actions.send(ActionEvent {
+                    sender: ev.sender,
+                    label: ActionLabel("options"),
+                    target: None,
+                });
+                continue;
+            }
+            label => {
+                if !NAME_CHARS.contains(label) || label.len() != 1 {
+                    continue;
+                }
+                name.get_or_insert_with(String::new).push_str(label);
+            }
+        }
+
+        let Some(current) = name.as_mut() else {
+            continue;
+        };
+        current.truncate(MAX_NAME_LEN);
+        let full = current.len() >= MAX_NAME_LEN;
+
+        let mut items: Vec<(MenuItem, ActionLabel, bool)> = (0..NAME_CHARS.len())
+            .map(|i| {
+                let c = &NAME_CHARS[i..=i];
+                (c.into(), ActionLabel(c), !full)
+            })
+            .collect();
+        items.extend([
+            ("Space".into(), ActionLabel("name space"), !full),
+            ("Delete".into(), ActionLabel("name delete"), true),
+            ("Done".into(), ActionLabel("name done"), true),
+        ]);
+
+        spawn.send(PopupMenuEvent {
+            sender: sender.entity(),
+            menu: PopupMenu {
+                heading: format!("Player Name\n{}_", current),
+                items,
+                cancel_action: Some(ActionLabel("name done")),
+                width: 9,
+                initial_position: match ev.label.0 {
+                    "edit name" => 0,
+                    _ => -1,
+                },
+                text_size: 30.0,
+                ..Default::default()
+            },
+            sound: false,
+        });
+    }
+}
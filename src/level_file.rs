@@ -0,0 +1,349 @@
+// hand authored levels
+//
+// a shape is either a list of [x, y] cells, or a grid of rows drawn top row first with '#' for a cell and
+// ' ' or '.' for a gap. the grid may be the `Display` output of a `CoordSet`, the [x,y] header and |-| border
+// are skipped. e.g.
+// {
+//   "title": "Curated",
+//   "levels": [
+//     {
+//       "name": "Two Squares",
+//       "plank": ["####", "####"],
+//       "holes": [
+//         { "at": [1, 1], "shape": ["##", "##"] },
+//         { "at": [4, 1], "shape": [[0, 0], [1, 0], [0, 1], [1, 1]] }
+//       ]
+//     }
+//   ]
+// }
+// `at` places the bottom left of the hole's shape in the hole area, which is centred above the plank.
+// packs are checked when they load: every level must be possible to cut and fill.
+
+use bevy::{prelude::*, reflect::TypeUuid};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    bit_grid::BitGrid,
+    model::{is_connected, solve_within, CoordSet, GaveUp, Holes, Level, Plank, Position},
+};
+
+// placements to try per level when a pack loads. a level the solver can't settle in time is let through
+// with a warning rather than holding up the load
+const CHECK_STEPS: usize = 1_000_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Shape {
+    Cells(Vec<[i32; 2]>),
+    Grid(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HoleFile {
+    pub at: [i32; 2],
+    pub shape: Shape,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LevelFile {
+    #[serde(default)]
+    pub name: String,
+    pub plank: Shape,
+    pub holes: Vec<HoleFile>,
+    // picks the wood grain
+    #[serde(default)]
+    pub seed: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TypeUuid)]
+#[uuid = "5b0b6e2c-3c1e-4d55-9f0e-86c1a2b7a4d3"]
+pub struct LevelPack {
+    pub title: String,
+    pub levels: Vec<LevelFile>,
+}
+
+#[derive(Debug)]
+pub enum LevelFileError {
+    Json(serde_json::Error),
+    BadChar(char),
+    Empty,
+    Disconnected,
+    Overlap,
+    AreaMismatch { plank: usize, holes: usize },
+    Unsolvable,
+}
+
+impl std::fmt::Display for LevelFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelFileError::Json(e) => write!(f, "{}", e),
+            LevelFileError::BadChar(c) => write!(f, "unexpected '{}' in grid", c),
+            LevelFileError::Empty => write!(f, "empty shape"),
+            LevelFileError::Disconnected => write!(f, "shape is not connected"),
+            LevelFileError::Overlap => write!(f, "holes overlap"),
+            LevelFileError::AreaMismatch { plank, holes } => {
+                write!(f, "plank has {} cells but holes have {}", plank, holes)
+            }
+            LevelFileError::Unsolvable => write!(f, "the plank can't be cut into the holes"),
+        }
+    }
+}
+
+impl std::error::Error for LevelFileError {}
+
+impl From<serde_json::Error> for LevelFileError {
+    fn from(e: serde_json::Error) -> Self {
+        LevelFileError::Json(e)
+    }
+}
+
+impl Shape {
+    /// a grid drawing of the cells, top row first
+    pub fn grid(cells: &CoordSet) -> Self {
+        let ((min_x, max_x), (min_y, max_y)) = cells.extents();
+        Shape::Grid(
+            (min_y..=max_y)
+                .rev()
+                .map(|y| {
+                    (min_x..=max_x)
+                        .map(|x| match cells.contains_xy(x, y) {
+                            true => '#',
+                            false => '.',
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// the shape's cells, normalized so the bottom left is at (0, 0)
+    pub fn to_coords(&self) -> Result<CoordSet, LevelFileError> {
+        let coords = self.to_cells()?;
+        if coords.count() == 0 {
+            return Err(LevelFileError::Empty);
+        }
+        if !is_connected(&coords.coords) {
+            return Err(LevelFileError::Disconnected);
+        }
+        Ok(coords)
+    }
+
+    /// the shape's cells without checking it is a single piece, for unfinished editor levels
+    pub fn to_cells(&self) -> Result<CoordSet, LevelFileError> {
+        let coords: BitGrid = match self {
+            Shape::Cells(cells) => cells.iter().map(|[x, y]| IVec2::new(*x, *y)).collect(),
+            Shape::Grid(rows) => {
+                let rows = rows
+                    .iter()
+                    .map(|row| row.trim_end())
+                    .filter(|row| !row.starts_with('[') && !row.starts_with("|-"))
+                    .map(|row| row.strip_prefix('|').unwrap_or(row))
+                    .map(|row| row.strip_suffix('|').unwrap_or(row))
+                    .collect::<Vec<_>>();
+
+                let mut coords = BitGrid::new();
+                for (y, row) in rows.iter().rev().enumerate() {
+                    for (x, c) in row.chars().enumerate() {
+                        match c {
+                            '#' => {
+                                coords.insert(IVec2::new(x as i32, y as i32));
+                            }
+                            ' ' | '.' => (),
+                            _ => return Err(LevelFileError::BadChar(c)),
+                        }
+                    }
+                }
+                coords
+            }
+        };
+
+        Ok(CoordSet {
+            coords,
+            ..Default::default()
+        }
+        .normalize())
+    }
+}
+
+impl LevelFile {
+    /// a level from painted cells. `holes` are in hole area coordinates, each connected piece becomes a hole
+    pub fn from_cells(name: String, plank: &CoordSet, holes: &CoordSet) -> Self {
+        let mut holes = holes
+            .connected_parts()
+            .into_iter()
+            .map(|hole| {
+                let (x, y) = hole.extents();
+                HoleFile {
+                    at: [x.0, y.0],
+                    shape: Shape::grid(&hole),
+                }
+            })
+            .collect::<Vec<_>>();
+        holes.sort_by_key(|hole| (hole.at[1], hole.at[0]));
+
+        Self {
+            name,
+            plank: Shape::grid(plank),
+            holes,
+            seed: 0,
+        }
+    }
+
+    /// build the level, laid out the same way as a generated level
+    pub fn to_level(&self) -> Result<Level, LevelFileError> {
+        let mut rng = Pcg32::seed_from_u64(self.seed);
+        let mut texture_offset =
+            || IVec2::new(rng.gen_range::<i32, _>(0..100), rng.gen_range(0..100));
+
+        let mut holes = Vec::new();
+        let mut extents = IVec2::ZERO;
+        for hole in self.holes.iter() {
+            let mut coords = hole.shape.to_coords()?;
+            coords.texture_offset = texture_offset();
+            coords.shift(IVec2::from(hole.at));
+            if holes.iter().any(|other: &CoordSet| other.overlaps(&coords)) {
+                return Err(LevelFileError::Overlap);
+            }
+            let hole_extents = coords.extents();
+            extents = extents.max(IVec2::new(hole_extents.0 .1, hole_extents.1 .1));
+            holes.push(coords);
+        }
+
+        let mut plank: Plank = self.plank.to_coords()?;
+        plank.texture_offset = texture_offset();
+        plank.shift(IVec2::ONE);
+
+        let hole_area = holes.iter().map(CoordSet::count).sum();
+        if plank.count() != hole_area {
+            return Err(LevelFileError::AreaMismatch {
+                plank: plank.count(),
+                holes: hole_area,
+            });
+        }
+
+        let size = plank.size() + 1;
+        let pos = IVec2::new(-size.x / 2, -size.y - 1);
+
+        Ok(Level {
+            extents,
+            holes: Holes { holes },
+            planks: vec![(plank, Position(pos))],
+            setup: true,
+            ..Default::default()
+        })
+    }
+}
+
+impl LevelPack {
+    /// parse a pack, checking every level builds and can be solved
+    pub fn from_json(json: &[u8]) -> Result<Self, LevelFileError> {
+        let pack: Self = serde_json::from_slice(json)?;
+        for level in pack.levels.iter() {
+            match solve_within(&level.to_level()?, CHECK_STEPS) {
+                Ok(Some(_)) => (),
+                Ok(None) => return Err(LevelFileError::Unsolvable),
+                Err(GaveUp) => warn!(
+                    "{}: gave up checking \"{}\" can be solved",
+                    pack.title, level.name
+                ),
+            }
+        }
+        Ok(pack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(rows: &[&str]) -> Shape {
+        Shape::Grid(rows.iter().map(|row| row.to_string()).collect())
+    }
+
+    #[test]
+    fn reads_display_output() {
+        let l = grid(&["#.", "#.", "##"]).to_coords().unwrap();
+        let printed = l.to_string();
+        let read = Shape::Grid(printed.lines().map(String::from).collect())
+            .to_coords()
+            .unwrap();
+        assert_eq!(read, l);
+        // the long side is on the left with the foot at the bottom right
+        assert!(read.contains_xy(0, 2));
+        assert!(read.contains_xy(1, 0));
+        assert!(!read.contains_xy(1, 2));
+    }
+
+    #[test]
+    fn grids_round_trip() {
+        let cells = Shape::Cells(vec![[0, 0], [1, 0], [1, 1], [2, 1]])
+            .to_coords()
+            .unwrap();
+        assert_eq!(Shape::grid(&cells), grid(&[".##", "##."]));
+        assert_eq!(Shape::grid(&cells).to_coords().unwrap(), cells);
+    }
+
+    #[test]
+    fn bad_shapes() {
+        assert!(matches!(
+            grid(&["#x"]).to_coords(),
+            Err(LevelFileError::BadChar('x'))
+        ));
+        assert!(matches!(
+            grid(&["#.#"]).to_coords(),
+            Err(LevelFileError::Disconnected)
+        ));
+        assert!(matches!(
+            grid(&[".."]).to_coords(),
+            Err(LevelFileError::Empty)
+        ));
+    }
+
+    #[test]
+    fn packs_are_checked() {
+        let pack = |plank: &[&str], holes: &[([i32; 2], &[&str])]| {
+            let pack = LevelPack {
+                title: "Test".into(),
+                levels: vec![LevelFile {
+                    name: String::new(),
+                    plank: grid(plank),
+                    holes: holes
+                        .iter()
+                        .map(|(at, shape)| HoleFile {
+                            at: *at,
+                            shape: grid(shape),
+                        })
+                        .collect(),
+                    seed: 0,
+                }],
+            };
+            LevelPack::from_json(&serde_json::to_vec(&pack).unwrap())
+        };
+
+        assert!(pack(&["####"], &[([0, 0], &["##"]), ([3, 0], &["#", "#"])]).is_ok());
+        assert!(matches!(
+            pack(&["###"], &[([0, 0], &["##"])]),
+            Err(LevelFileError::AreaMismatch { plank: 3, holes: 2 })
+        ));
+        assert!(matches!(
+            pack(&["####"], &[([0, 0], &["###", ".#."])]),
+            Err(LevelFileError::Unsolvable)
+        ));
+        assert!(matches!(
+            pack(&["##"], &[([0, 0], &["#"]), ([0, 0], &["#"])]),
+            Err(LevelFileError::Overlap)
+        ));
+        assert!(matches!(
+            LevelPack::from_json(b"{}"),
+            Err(LevelFileError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn curated_pack_loads() {
+        let pack = LevelPack::from_json(include_bytes!("../assets/levels/curated.pack.json"));
+        assert!(pack.is_ok(), "{}", pack.unwrap_err());
+    }
+}
@@ -0,0 +1,482 @@
+// level recipes and building levels from them
+
+use std::borrow::Cow;
+
+use bevy::{prelude::*, utils::HashSet};
+use rand::{Rng, SeedableRng};
+use rand_pcg::Pcg32;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level_file::{LevelFile, LevelPack},
+    model::{gen_holes, solve_within, Hole, Level, Plank, Position},
+};
+
+// placements to try when working out par. a solution always takes holes - planks cuts, which stands in if
+// the solver gives up
+const PAR_STEPS: usize = 100_000;
+
+// the biggest level the generator is asked for. random and endless sets stay under it
+pub const MAX_HOLES: usize = 20;
+pub const MAX_BLOCKS: usize = 150;
+// the most defs a set from the menus is drawn from
+pub const MAX_SET_TOTAL: usize = 90;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct LevelDef {
+    pub num_holes: usize,
+    pub total_blocks: usize,
+    pub seed: u64,
+    // hand authored levels are built from the file instead of the generator
+    #[serde(default)]
+    pub file: Option<LevelFile>,
+}
+
+impl LevelDef {
+    /// check `gen_holes` can split `total_blocks` into `num_holes` holes of between half and one and a half
+    /// times the average size
+    pub fn check_generator(&self) -> Result<(), String> {
+        if self.num_holes == 0 {
+            return Err("need at least one hole".into());
+        }
+        let avg = self.total_blocks as f32 / self.num_holes as f32;
+        let smallest = ((avg * 0.5).ceil() as usize).max(1);
+        let largest = (avg * 1.5).floor() as usize;
+        if self.total_blocks < self.num_holes * smallest {
+            return Err(format!("need at least {} blocks", self.num_holes));
+        }
+        if smallest > largest || self.total_blocks > self.num_holes * largest {
+            return Err(format!(
+                "can't split {} blocks into {} holes",
+                self.total_blocks, self.num_holes
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct LevelSet {
+    pub levels: Vec<LevelDef>,
+    pub current_level: usize,
+    pub title: String,
+    // progress is stored under this key, most sets have a fixed one
+    pub settings_key: Cow<'static, str>,
+    // how a random set was generated, so it can be shared
+    pub recipe: Option<SetRecipe>,
+}
+
+// sets from generator 1 on draw again for a level that repeats an earlier one. generator 0 keeps it, so
+// its sets and their share codes stay the same
+pub const SET_GENERATOR: u32 = 1;
+
+/// the inputs to `spawn_random`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetRecipe {
+    pub total: usize,
+    pub skip: usize,
+    pub count: usize,
+    pub seed: u64,
+    pub title: String,
+    #[serde(default)]
+    pub generator: u32,
+}
+
+impl SetRecipe {
+    pub fn build(&self, key: &'static str) -> LevelSet {
+        spawn_random(
+            self.total,
+            self.skip,
+            self.count,
+            self.title.clone(),
+            self.seed,
+            self.generator,
+            key,
+        )
+    }
+}
+
+/// generate `total` random level defs from the seed, sort them by difficulty and keep up to `count` starting from `skip`
+pub fn spawn_random(
+    total: usize,
+    skip: usize,
+    count: usize,
+    title: String,
+    seed: u64,
+    generator: u32,
+    key: impl Into<Cow<'static, str>>,
+) -> LevelSet {
+    let mut rng = Pcg32::seed_from_u64(seed);
+    let mut draw = || {
+        let numbers = (
+            rng.gen::<u64>(),
+            rng.gen_range::<u64, _>(2..15),
+            rng.gen_range::<u64, _>(0..7),
+            rng.gen_range::<u64, _>(3..9),
+        );
+
+        let seed = numbers.0;
+        let num_holes = numbers.1;
+        let total_blocks = numbers.2 + num_holes * numbers.3;
+        LevelDef {
+            num_holes: num_holes as usize,
+            total_blocks: total_blocks as usize,
+            seed,
+            file: None,
+        }
+    };
+
+    // different seeds can make the same puzzle. there are far more puzzles than a set holds, so drawing again
+    // finds a new one soon
+    let mut defs = Vec::new();
+    let mut seen = HashSet::default();
+    let mut repeats = 0;
+    while defs.len() < total {
+        let def = draw();
+        let level = generate(&def);
+        if !seen.insert(level.shape_keys()) {
+            repeats += 1;
+            if generator > 0 {
+                continue;
+            }
+        }
+        defs.push(((level.difficulty() * 100000.0) as i32, def));
+    }
+    debug!("{}: {} repeated levels", title, repeats);
+
+    defs.sort_by_key(|(difficulty, _)| *difficulty);
+
+    let defs = defs
+        .into_iter()
+        .skip(skip)
+        .take(count)
+        .map(|(_, def)| def)
+        .collect();
+
+    LevelSet {
+        title: title.clone(),
+        levels: defs,
+        current_level: 0,
+        settings_key: key.into(),
+        recipe: Some(SetRecipe {
+            total,
+            skip,
+            count,
+            seed,
+            title,
+            generator,
+        }),
+    }
+}
+
+// the holes and plank of a generated def, without laying them out
+fn generate(def: &LevelDef) -> Level {
+    let mut rng = Pcg32::seed_from_u64(def.seed);
+    let holes = gen_holes(def.num_holes, def.total_blocks, &mut rng);
+    let plank = Plank::from_holes(&holes, &mut rng);
+    Level {
+        holes,
+        planks: vec![(plank, Position::default())],
+        ..Default::default()
+    }
+}
+
+/// difficulty score of a generated def, without laying it out
+pub fn difficulty(def: &LevelDef) -> f32 {
+    generate(def).difficulty()
+}
+
+// endless runs ramp up to the size of the hardest random levels
+const ENDLESS_CANDIDATES: usize = 8;
+const ENDLESS_MAX_HOLES: usize = 14;
+const ENDLESS_MAX_BLOCKS_PER_HOLE: usize = 8;
+
+/// the first level of an endless run, and a set to grow with `next_endless`
+pub fn endless_set(seed: u64, key: &'static str) -> LevelSet {
+    LevelSet {
+        title: "Endless".into(),
+        levels: vec![LevelDef {
+            num_holes: 2,
+            total_blocks: 7,
+            seed,
+            file: None,
+        }],
+        current_level: 0,
+        settings_key: key.into(),
+        recipe: None,
+    }
+}
+
+/// the level after `last` in an endless run: the easiest of a few slightly bigger candidates that scores
+/// harder than `last`, or the hardest of them once the sizes stop growing
+pub fn next_endless(last: &LevelDef) -> LevelDef {
+    let mut rng = Pcg32::seed_from_u64(last.seed);
+    let last_difficulty = difficulty(last);
+
+    let candidates = (0..ENDLESS_CANDIDATES).map(|_| {
+        let num_holes = (last.num_holes + rng.gen_range(0..=1)).min(ENDLESS_MAX_HOLES);
+        let total_blocks = (last.total_blocks + rng.gen_range(0..=2))
+            .max(num_holes * 3)
+            .min(num_holes * ENDLESS_MAX_BLOCKS_PER_HOLE);
+        let def = LevelDef {
+            num_holes,
+            total_blocks,
+            seed: rng.gen(),
+            file: None,
+        };
+        (difficulty(&def), def)
+    });
+
+    let (harder, easier): (Vec<_>, Vec<_>) =
+        candidates.partition(|(difficulty, _)| *difficulty > last_difficulty);
+    let by_difficulty = |a: &&(f32, LevelDef), b: &&(f32, LevelDef)| a.0.total_cmp(&b.0);
+    harder
+        .iter()
+        .min_by(by_difficulty)
+        .or_else(|| easier.iter().max_by(by_difficulty))
+        .unwrap()
+        .1
+        .clone()
+}
+
+/// a set playing through a pack's levels in order
+pub fn pack_set(pack: &LevelPack, key: &'static str) -> LevelSet {
+    LevelSet {
+        title: pack.title.clone(),
+        levels: pack
+            .levels
+            .iter()
+            .enumerate()
+            .map(|(i, file)| {
+                let holes = file.holes.len();
+                LevelDef {
+                    num_holes: holes,
+                    total_blocks: file.plank.to_coords().map_or(0, |plank| plank.count()),
+                    seed: i as u64,
+                    file: Some(file.clone()),
+                }
+            })
+            .collect(),
+        current_level: 0,
+        settings_key: key.into(),
+        recipe: None,
+    }
+}
+
+/// generate the holes and plank for a def and lay them out: holes in a grid above the origin, the plank below
+pub fn build_level(def: &LevelDef) -> Level {
+    if let Some(file) = def.file.as_ref() {
+        // packs are checked when they are loaded
+        return with_par(file.to_level().unwrap());
+    }
+
+    let mut rng = Pcg32::seed_from_u64(def.seed);
+    let mut holes = gen_holes(def.num_holes, def.total_blocks, &mut rng);
+    holes
+        .holes
+        .sort_by(|a, b| a.size().y.cmp(&b.size().y).reverse());
+    let mut plank = Plank::from_holes(&holes, &mut rng);
+    if plank.size().x < plank.size().y {
+        plank.rotate();
+        plank = plank.normalize();
+    }
+    plank.shift(IVec2::ONE);
+
+    // arrange
+    let count = holes.holes.len();
+    let grid_y = (count as f32 / 2.0).sqrt().floor() as usize;
+    let grid_x = (count as f32 / grid_y as f32).ceil() as usize;
+
+    debug!("build_level: count: {}, grid: {},{}", count, grid_x, grid_y);
+
+    let mut extents = IVec2::ZERO;
+    let mut grid_col = 0;
+    let mut x_off = 1;
+    let mut y_off = 1;
+    let mut max_y_row = 0;
+
+    for hole in holes.holes.iter_mut() {
+        hole.shift(IVec2::new(x_off, y_off));
+        let hole_extents = hole.extents();
+        max_y_row = max_y_row.max(hole_extents.1 .1);
+        x_off = hole_extents.0 .1 + 2;
+        extents = extents.max(IVec2::new(hole_extents.0 .1, hole_extents.1 .1));
+        grid_col += 1;
+        if grid_col == grid_x {
+            grid_col = 0;
+            x_off = 1;
+            y_off = max_y_row + 2;
+            max_y_row = 0;
+        }
+    }
+
+    let uber_hole = Hole::merge(holes.holes.iter());
+    debug!("uber hole: [{:?}] \n{}", uber_hole.extents(), uber_hole);
+    debug!("plank: [{:?}]\n{}", plank.extents(), plank);
+
+    let size = plank.size() + 1;
+    let pos = IVec2::new(-size.x / 2, -size.y - 1);
+
+    with_par(Level {
+        extents,
+        holes,
+        planks: vec![(plank, Position(pos))],
+        setup: true,
+        ..Default::default()
+    })
+}
+
+// par from the solver's cut count
+fn with_par(mut level: Level) -> Level {
+    level.par = match solve_within(&level, PAR_STEPS) {
+        Ok(Some(solution)) => solution.cut_count(),
+        _ => level.holes.holes.len().saturating_sub(level.planks.len()),
+    };
+    level
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::solve;
+
+    fn def(num_holes: usize, total_blocks: usize, seed: u64) -> LevelDef {
+        LevelDef {
+            num_holes,
+            total_blocks,
+            seed,
+            file: None,
+        }
+    }
+
+    #[test]
+    fn generated_levels_are_reproducible_and_solvable() {
+        for seed in 0..10 {
+            let def = def(4, 16, seed);
+            let level = build_level(&def);
+            assert_eq!(level.holes.holes.len(), 4);
+            let hole_area: usize = level.holes.holes.iter().map(|hole| hole.count()).sum();
+            assert_eq!(hole_area, 16);
+            assert_eq!(level.planks[0].0.count(), 16);
+
+            let again = build_level(&def);
+            assert_eq!(again.planks[0].0, level.planks[0].0);
+            assert_eq!(again.holes.holes, level.holes.holes);
+
+            let solution = solve(&level).expect("generated levels can be solved");
+            assert_eq!(solution.cut_count(), 3);
+            assert_eq!(level.par, 3);
+        }
+    }
+
+    #[test]
+    fn generator_ranges() {
+        assert!(def(0, 5, 0).check_generator().is_err());
+        assert!(def(5, 4, 0).check_generator().is_err());
+        assert!(def(3, 9, 0).check_generator().is_ok());
+        assert!(def(1, 1, 0).check_generator().is_ok());
+    }
+
+    #[test]
+    fn random_sets_follow_the_recipe() {
+        let recipe = SetRecipe {
+            total: 20,
+            skip: 5,
+            count: 10,
+            seed: 7,
+            title: "Test".into(),
+            generator: SET_GENERATOR,
+        };
+        let set = recipe.build("test");
+        assert_eq!(set.levels.len(), 10);
+        assert_eq!(set.recipe.as_ref(), Some(&recipe));
+        let again = recipe.build("test");
+        let seeds = |set: &LevelSet| set.levels.iter().map(|def| def.seed).collect::<Vec<_>>();
+        assert_eq!(seeds(&set), seeds(&again));
+    }
+
+    #[test]
+    fn first_generator_is_unchanged() {
+        let seeds = |set: &LevelSet| set.levels.iter().map(|def| def.seed).collect::<Vec<_>>();
+        let easy = spawn_random(90, 0, 30, "Easy Set".into(), 11, 0, "Easy");
+        assert_eq!(easy.levels.len(), 30);
+        assert_eq!(
+            seeds(&easy)[..4],
+            [
+                1206363094216636733,
+                534451946001363761,
+                14317549094584898896,
+                12030027762110814019
+            ]
+        );
+        let hard = spawn_random(90, 60, 30, "Hard Set".into(), 15, 0, "Hard");
+        assert_eq!(hard.levels.len(), 30);
+        assert_eq!(
+            seeds(&hard)[26..],
+            [
+                10202095167191570156,
+                915860122561672400,
+                11086948908604747212,
+                10292389994125879795
+            ]
+        );
+
+        // and the holes laid out the same
+        let level = build_level(&easy.levels[29]);
+        let holes = level
+            .holes
+            .holes
+            .iter()
+            .map(|hole| hole.coords.iter().map(|c| (c.x, c.y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            holes,
+            [
+                vec![
+                    (1, 1),
+                    (2, 1),
+                    (2, 2),
+                    (1, 3),
+                    (2, 3),
+                    (1, 4),
+                    (2, 4),
+                    (2, 5)
+                ],
+                vec![
+                    (6, 1),
+                    (4, 2),
+                    (5, 2),
+                    (6, 2),
+                    (7, 2),
+                    (6, 3),
+                    (7, 3),
+                    (6, 4),
+                    (7, 4)
+                ],
+                vec![(10, 1), (10, 2), (9, 3), (10, 3), (10, 4), (11, 4)],
+                vec![(14, 1), (14, 2), (13, 3), (14, 3), (13, 4), (14, 4)],
+                vec![(17, 1), (17, 2), (16, 3), (17, 3)],
+                vec![(19, 1), (20, 1), (19, 2)],
+            ]
+        );
+    }
+
+    #[test]
+    fn sets_have_no_repeats() {
+        let keys = |set: &LevelSet| {
+            set.levels
+                .iter()
+                .map(|def| build_level(def).shape_keys())
+                .collect::<HashSet<_>>()
+        };
+
+        // repeats are rare, the first generator keeps one from this seed
+        let old = spawn_random(90, 0, 90, "Test".into(), 1377, 0, "test");
+        assert_eq!(old.levels.len(), 90);
+        assert_eq!(keys(&old).len(), 89);
+
+        let set = spawn_random(90, 0, 90, "Test".into(), 1377, SET_GENERATOR, "test");
+        assert_eq!(set.levels.len(), 90);
+        assert_eq!(keys(&set).len(), 90);
+    }
+}
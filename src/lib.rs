@@ -0,0 +1,10 @@
+// the puzzle rules without rendering or input. the game binary is a frontend over these
+
+pub mod bit_grid;
+pub mod cut;
+pub mod game;
+pub mod level_file;
+pub mod levels;
+pub mod model;
+pub mod share;
+pub mod stats;
@@ -28,24 +28,26 @@ const PLANK_Z_HILIGHTED: f32 = 0.75;
 const PLANK_Z_DONE: f32 = 0.25;
 
 use bevy_pkv::PkvStore;
-use input::{Action, ActionType, Controller, DisplayDirections, DisplayMode, InputPlugin};
+use input::{
+    Action, ActionType, AnalogPace, Controller, DisplayDirections, DisplayMode, InputPlugin,
+};
 use menus::{
     spawn_controls, spawn_in_level_menu, spawn_main_menu, spawn_play_menu, spawn_popup_menu,
     PopupMenuEvent,
 };
-use rand::{prelude::SliceRandom, thread_rng, Rng, SeedableRng};
+use rand::{prelude::SliceRandom, thread_rng, Rng};
 
 use bevy::{
     app::AppExit,
-    ecs::event::{Events, ManualEventReader},
+    ecs::{
+        event::{Events, ManualEventReader},
+        system::SystemParam,
+    },
     log::LogSettings,
     math::Vec3Swizzles,
     prelude::{shape::UVSphere, *},
-    render::{
-        camera::Camera3d,
-        render_resource::{Extent3d, TextureDimension},
-    },
-    utils::{HashMap, HashSet},
+    render::camera::Camera3d,
+    utils::HashMap,
     window::WindowResized,
 };
 
@@ -57,44 +59,72 @@ use bevy_kira_audio::{AudioApp, AudioChannel, AudioPlugin};
 
 mod background;
 mod bl_quad;
+mod daily;
+mod diff;
+mod editor;
+mod endless;
+mod fit;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod hint;
 mod input;
+mod leaderboard;
 mod menus;
-mod model;
-mod shader;
+mod mouse;
+mod packs;
+mod replay;
+mod resume;
 mod structs;
+mod touch;
+#[cfg(target_arch = "wasm32")]
+mod web_touch;
 mod window;
 mod wood_material;
 
+use bevy_carp::{
+    cut::{split_plank, Cut, CutStep},
+    levels::build_level,
+    model::*,
+    stats::LevelStats,
+};
 use bl_quad::BLQuad;
-use model::*;
-use rand_pcg::Pcg32;
-use shader::SimpleTextureMaterial;
 use structs::{
     ActionEvent, ActionLabel, ChangeBackground, ControlHelp, GrabDropChannel, HammerChannel,
     LevelDef, LevelSet, MenuChannel, MusicChannel, Permanent, PositionZ, SpawnLevelEvent,
     UndoChannel,
 };
 use window::{descriptor_from_settings, WindowModeSerial};
-use wood_material::{WoodMaterial, WoodMaterialPlugin, WoodMaterialSpec};
+use wood_material::{WoodMaterial, WoodMaterialPlugin};
 
 use crate::{
     background::BackgroundPlugin,
-    menus::{spawn_credits, spawn_options_menu, PopupMenu},
+    daily::DailyPlugin,
+    diff::DiffPlugin,
+    editor::{EditorControls, EditorPlugin, EDITOR_KEY},
+    endless::{EndlessPlugin, ENDLESS_KEY},
+    fit::FitPlugin,
+    hint::HintPlugin,
+    leaderboard::LeaderboardPlugin,
+    menus::{spawn_credits, spawn_custom_menu, spawn_options_menu, spawn_share_menu, PopupMenu},
+    mouse::MousePlugin,
+    packs::LevelPackPlugin,
+    replay::{Replay, ReplayPlugin},
+    resume::ResumePlugin,
     structs::{CutChannel, Position, SwooshChannel, QUIT_TO_DESKTOP},
+    touch::TouchPlugin,
 };
 
 fn main() {
-    // stage conventions to avoid adding components to despawned entities:
-    // pre - ensure working state
-    // update - add / spawn, don't despawn (unless it's locally used entities)
-    // post - spawn / despawn, don't add
+    #[cfg(not(target_arch = "wasm32"))]
+    if std::env::args().any(|arg| arg == "--headless") {
+        headless::run();
+        return;
+    }
 
     let settings = PkvStore::new("robtfm", "measure once");
 
     let window_descriptor = descriptor_from_settings(&settings);
     let control_help = ControlHelp(settings.get("control help").unwrap_or(true));
-    let cursor_speed = CursorSpeed(settings.get("cursor speed").unwrap_or(15.0));
-    let cut_speed = CutSpeed(settings.get("cut speed").unwrap_or(5.0));
     let music_volume = MusicVolume(settings.get("music volume").unwrap_or(0.5));
     let sfx_volume = SfxVolume(settings.get("sfx volume").unwrap_or(0.5));
 
@@ -110,6 +140,18 @@ fn main() {
         .add_plugin(AudioPlugin)
         .add_plugin(InputPlugin)
         .add_plugin(BackgroundPlugin)
+        .add_plugin(HintPlugin)
+        .add_plugin(FitPlugin)
+        .add_plugin(DiffPlugin)
+        .add_plugin(MousePlugin)
+        .add_plugin(TouchPlugin)
+        .add_plugin(EditorPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(ResumePlugin)
+        .add_plugin(EndlessPlugin)
+        .add_plugin(DailyPlugin)
+        .add_plugin(LeaderboardPlugin)
+        .add_plugin(LevelPackPlugin)
         .add_audio_channel::<MusicChannel>()
         .add_audio_channel::<MenuChannel>()
         .add_audio_channel::<GrabDropChannel>()
@@ -117,30 +159,14 @@ fn main() {
         .add_audio_channel::<HammerChannel>()
         .add_audio_channel::<CutChannel>()
         .add_audio_channel::<UndoChannel>()
-        .init_resource::<Level>()
-        .init_resource::<DonePlanks>()
-        .init_resource::<LevelDef>()
-        .init_resource::<LevelBase>()
-        .init_resource::<LevelSet>()
-        .init_resource::<UndoBuffer>()
-        .insert_resource(settings)
         .insert_resource(AmbientLight {
             color: Color::rgba(0.8, 0.8, 1.0, 1.0),
             brightness: 0.1,
         })
         .insert_resource(ClearColor(Color::rgb(0.05, 0.05, 0.3)))
         .insert_resource(control_help)
-        .insert_resource(cursor_speed)
-        .insert_resource(cut_speed)
         .insert_resource(music_volume)
         .insert_resource(sfx_volume)
-        .add_event::<SpawnLevelEvent>()
-        .add_event::<PopupMenuEvent>()
-        .add_event::<CutEvent>()
-        .add_event::<ResetEvent>()
-        .add_event::<SnapUndo>()
-        .add_event::<SpawnNail>()
-        .add_event::<SpawnPlank>()
         // egui
         .add_startup_system(warm_assets)
         .add_startup_system(egui_setup)
@@ -153,7 +179,46 @@ fn main() {
         .add_system(spawn_credits)
         .add_system(spawn_popup_menu)
         .add_system(spawn_options_menu)
+        .add_system(spawn_custom_menu)
+        .add_system(spawn_share_menu)
         .add_system(spawn_controls)
+        // fx
+        .add_system(update_materials)
+        .add_system(update_volumes)
+        .add_system(update_speed_settings);
+
+    add_level_systems(&mut app, settings);
+    // app.add_system(debug_actions);
+    app.run();
+}
+
+/// the level resources and mechanics, shared with the headless app
+fn add_level_systems(app: &mut App, settings: PkvStore) {
+    // stage conventions to avoid adding components to despawned entities:
+    // pre - ensure working state
+    // update - add / spawn, don't despawn (unless it's locally used entities)
+    // post - spawn / despawn, don't add
+
+    let cursor_speed = CursorSpeed(settings.get("cursor speed").unwrap_or(15.0));
+    let cut_speed = CutSpeed(settings.get("cut speed").unwrap_or(5.0));
+
+    app.init_resource::<Level>()
+        .init_resource::<DonePlanks>()
+        .init_resource::<LevelDef>()
+        .init_resource::<LevelBase>()
+        .init_resource::<LevelSet>()
+        .init_resource::<UndoBuffer>()
+        .init_resource::<LevelStats>()
+        .insert_resource(settings)
+        .insert_resource(cursor_speed)
+        .insert_resource(cut_speed)
+        .add_event::<SpawnLevelEvent>()
+        .add_event::<PopupMenuEvent>()
+        .add_event::<CutEvent>()
+        .add_event::<ResetEvent>()
+        .add_event::<SnapUndo>()
+        .add_event::<SpawnNail>()
+        .add_event::<SpawnPlank>()
         // setup level
         .add_system(setup_level) // generate the level from the def
         .add_system_to_stage(CoreStage::PreUpdate, create_level) // (re)spawn a level. should have its own stage really
@@ -169,14 +234,12 @@ fn main() {
         .add_system(update_positions.before(update_transforms))
         .add_system(update_transforms)
         .add_system(check_cut_actions)
+        .add_system(track_stats)
         // fx
-        .add_system(update_materials)
         .add_system(spawn_planks)
         .add_system(spawn_nails)
         .add_system(animate_cuts)
         .add_system(animate_sparks)
-        .add_system(update_volumes)
-        .add_system(update_speed_settings)
         // system events
         .add_system_to_stage(CoreStage::PostUpdate, system_events)
         // undo/redo
@@ -184,9 +247,7 @@ fn main() {
         .add_system_to_stage(CoreStage::PostUpdate, record_state.after(cut_plank))
         .add_system_to_stage(CoreStage::PostUpdate, change_state)
         // camera management
-        .add_system_to_stage(CoreStage::PostUpdate, camera_focus)
-        // .add_system(debug_actions)
-        .run();
+        .add_system_to_stage(CoreStage::PostUpdate, camera_focus);
 }
 
 #[derive(Default)]
@@ -201,10 +262,15 @@ fn splash(
     mut evs: EventWriter<ActionEvent>,
     audio: Res<AudioChannel<MusicChannel>>,
     server: Res<AssetServer>,
+    replay: Res<Replay>,
 ) {
+    let label = match replay.pending {
+        Some(_) => "watch replay",
+        None => "main menu",
+    };
     evs.send(ActionEvent {
         sender: Entity::from_raw(99),
-        label: ActionLabel("main menu"),
+        label: ActionLabel(label),
         target: None,
     });
 
@@ -373,62 +439,6 @@ fn handle_window_resize(
     events_2.iter().count();
 }
 
-fn spawn_random(
-    total: usize,
-    skip: usize,
-    title: String,
-    seed: u64,
-    key: &'static str,
-) -> LevelSet {
-    let mut rng = Pcg32::seed_from_u64(seed);
-
-    let mut defs = (0..total)
-        .map(|_| {
-            let numbers = (
-                rng.gen::<u64>(),
-                rng.gen_range::<u64, _>(2..15),
-                rng.gen_range::<u64, _>(0..7),
-                rng.gen_range::<u64, _>(3..9),
-            );
-
-            let seed = numbers.0;
-            let num_holes = numbers.1;
-            let total_blocks = numbers.2 + num_holes * numbers.3;
-            LevelDef {
-                num_holes: num_holes as usize,
-                total_blocks: total_blocks as usize,
-                seed,
-            }
-        })
-        .collect::<Vec<_>>();
-
-    defs.sort_by_key(|def| {
-        let mut rng = Pcg32::seed_from_u64(def.seed);
-        let holes = gen_holes(def.num_holes, def.total_blocks, &mut rng);
-        let plank = Plank::from_holes(&holes, &mut rng);
-        let level = Level {
-            holes,
-            planks: vec![(plank, Position::default())],
-            ..Default::default()
-        };
-        (level.difficulty() * 100000.0) as i32
-    });
-
-    defs = defs.into_iter().skip(skip).take(30).collect();
-
-    let defs: [LevelDef; 30] = match defs.try_into() {
-        Ok(defs) => defs,
-        Err(_) => panic!(),
-    };
-
-    LevelSet {
-        title,
-        levels: defs,
-        current_level: 0,
-        settings_key: key,
-    }
-}
-
 fn setup_level(
     mut spawn_evs: EventReader<SpawnLevelEvent>,
     mut base: ResMut<LevelBase>,
@@ -437,63 +447,13 @@ fn setup_level(
     mut reset: EventWriter<ResetEvent>,
     mut commands: Commands,
     mut bg: EventWriter<ChangeBackground>,
+    mut stats: ResMut<LevelStats>,
 ) {
     for ev in spawn_evs.iter() {
-        let mut rng = Pcg32::seed_from_u64(ev.def.seed);
-        let mut holes = gen_holes(ev.def.num_holes, ev.def.total_blocks, &mut rng);
-        holes
-            .holes
-            .sort_by(|a, b| a.size().y.cmp(&b.size().y).reverse());
-        let mut plank = Plank::from_holes(&holes, &mut rng);
-        if plank.size().x < plank.size().y {
-            plank.rotate();
-            plank = plank.normalize();
-        }
-        plank.shift(IVec2::ONE);
-
-        // arrange
-        let count = holes.holes.len();
-        let grid_y = (count as f32 / 2.0).sqrt().floor() as usize;
-        let grid_x = (count as f32 / grid_y as f32).ceil() as usize;
-
-        debug!("setup_level: count: {}, grid: {},{}", count, grid_x, grid_y);
-
-        let mut extents = IVec2::ZERO;
-        let mut grid_col = 0;
-        let mut x_off = 1;
-        let mut y_off = 1;
-        let mut max_y_row = 0;
-
-        for hole in holes.holes.iter_mut() {
-            hole.shift(IVec2::new(x_off, y_off));
-            let hole_extents = hole.extents();
-            max_y_row = max_y_row.max(hole_extents.1 .1);
-            x_off = hole_extents.0 .1 + 2;
-            extents = extents.max(IVec2::new(hole_extents.0 .1, hole_extents.1 .1));
-            grid_col += 1;
-            if grid_col == grid_x {
-                grid_col = 0;
-                x_off = 1;
-                y_off = max_y_row + 2;
-                max_y_row = 0;
-            }
-        }
-
-        let uber_hole = Hole::merge(holes.holes.iter());
-        debug!("uber hole: [{:?}] \n{}", uber_hole.extents(), uber_hole);
-        debug!("plank: [{:?}]\n{}", plank.extents(), plank);
-
-        let size = plank.size() + 1;
-        let pos = IVec2::new(-size.x / 2, -size.y - 1);
-
-        *base = LevelBase(Level {
-            extents,
-            holes,
-            planks: vec![(plank, Position(pos))],
-            setup: true,
-        });
+        *base = LevelBase(build_level(&ev.def));
         *level = base.0.clone();
         *def = ev.def.clone();
+        *stats = LevelStats::new(base.0.par);
         commands.insert_resource(UndoBuffer::new(base.0.clone()));
         commands.insert_resource(DonePlanks::default());
 
@@ -502,29 +462,6 @@ fn setup_level(
     }
 }
 
-fn create_coordset_image<'a>(images: &mut Assets<Image>, coords: &CoordSet) -> Handle<Image> {
-    let size = IVec2::new(coords.extents().0 .1 + 1, coords.extents().1 .1 + 1);
-    debug!("dims: {}", size);
-    let mut data = Vec::from_iter(std::iter::repeat(0u8).take((size.x * size.y) as usize));
-
-    for coord in coords.coords.iter() {
-        data[(coord.x + coord.y * size.x) as usize] = 1;
-    }
-
-    let image = Image::new(
-        Extent3d {
-            width: size.x as u32,
-            height: size.y as u32,
-            depth_or_array_layers: 1,
-        },
-        TextureDimension::D2,
-        data,
-        bevy::render::render_resource::TextureFormat::R8Uint,
-    );
-
-    images.add(image)
-}
-
 pub struct CursorSpeed(pub f32);
 pub struct CutSpeed(pub f32);
 
@@ -577,14 +514,22 @@ fn create_level(
                             display_text: None,
                         },
                     ),
+                    (
+                        ActionType::Hint,
+                        Action {
+                            label: ActionLabel("hint"),
+                            sticky: true,
+                            display: DisplayMode::Active,
+                            display_text: None,
+                        },
+                    ),
                 ],
                 enabled: true,
                 ..Default::default()
             })
             .insert(SystemController);
 
-        let size = level.extents;
-        let pos = IVec2::new(-size.x / 2, 1);
+        let pos = level.hole_origin();
         let merger = CoordSet::merge(level.holes.holes.iter());
         spawn_planks.send(SpawnPlank {
             plank: merger,
@@ -724,6 +669,7 @@ fn create_level(
             .insert(ExtentItem(IVec2::ONE, IVec2::ONE))
             .insert(Cursor)
             .insert(MoveSpeed(cursor_speed.0))
+            .insert(AnalogPace::default())
             .insert(Controller {
                 display_order: 2,
                 display_directions: Some(DisplayDirections {
@@ -910,19 +856,23 @@ fn update_transforms(
         Option<&PositionZ>,
         Option<&PositionOffset>,
         Option<&MoveSpeed>,
+        Option<&AnalogPace>,
     )>,
 ) {
-    for (mut transform, position, maybe_posz, maybe_offset, maybe_speed) in q.iter_mut() {
+    for (mut transform, position, maybe_posz, maybe_offset, maybe_speed, maybe_pace) in q.iter_mut()
+    {
         let position = position.0.as_vec2() + maybe_offset.map(|o| o.0).unwrap_or_default();
 
         let speed = maybe_speed.unwrap_or(&MoveSpeed(15.0)).0;
+        let pace = maybe_pace.map_or(Vec2::ONE, |p| p.0);
         if transform.translation.x < position.x as f32 {
             transform.translation.x = f32::min(
                 position.x as f32,
                 transform.translation.x
                     + time.delta_seconds()
                         * f32::max(1.0, position.x - transform.translation.x)
-                        * speed,
+                        * speed
+                        * pace.x,
             );
         } else {
             transform.translation.x = f32::max(
@@ -930,7 +880,8 @@ fn update_transforms(
                 transform.translation.x
                     - time.delta_seconds()
                         * f32::max(1.0, transform.translation.x - position.x)
-                        * speed,
+                        * speed
+                        * pace.x,
             );
         }
         if transform.translation.y < position.y as f32 {
@@ -939,7 +890,8 @@ fn update_transforms(
                 transform.translation.y
                     + time.delta_seconds()
                         * f32::max(1.0, position.y - transform.translation.y)
-                        * speed,
+                        * speed
+                        * pace.y,
             );
         } else {
             transform.translation.y = f32::max(
@@ -947,7 +899,8 @@ fn update_transforms(
                 transform.translation.y
                     - time.delta_seconds()
                         * f32::max(1.0, transform.translation.y - position.y)
-                        * speed,
+                        * speed
+                        * pace.y,
             );
         }
 
@@ -976,16 +929,16 @@ fn update_transforms(
 }
 
 #[derive(Component)]
-struct Selected;
+pub struct Selected;
 
 #[derive(Component)]
-struct Targeted;
+pub struct Targeted;
 
 #[derive(Component)]
-struct Cursor;
+pub struct Cursor;
 
 #[derive(Component)]
-struct PlankComponent(Plank, Handle<WoodMaterial>);
+pub struct PlankComponent(pub Plank, pub Entity);
 
 #[derive(Default, Clone)]
 struct DonePlanks(Vec<(Plank, Position, Vec<IVec2>)>);
@@ -994,32 +947,44 @@ struct DonePlanks(Vec<(Plank, Position, Vec<IVec2>)>);
 struct MHoles;
 
 #[derive(Component)]
-struct SystemController;
+pub struct SystemController;
+
+/// whether the level is being played: menus disable the level's controllers
+#[derive(SystemParam)]
+pub struct LevelControls<'w, 's> {
+    system: Query<'w, 's, &'static Controller, With<SystemController>>,
+}
+
+impl LevelControls<'_, '_> {
+    pub fn enabled(&self) -> bool {
+        self.system.iter().any(|controller| controller.enabled)
+    }
+}
 
 #[derive(Component)]
 struct RotateAround(IVec2);
 
 fn update_materials(
     time: Res<Time>,
-    mut mats: ResMut<Assets<WoodMaterial>>,
+    mut mats: Query<&mut WoodMaterial>,
     selected: Query<&PlankComponent, With<Selected>>,
     targeted: Query<&PlankComponent, With<Targeted>>,
     neither: Query<&PlankComponent, (Without<Selected>, Without<Targeted>)>,
 ) {
     let mult = (((time.seconds_since_startup() * 6.0).sin() + 1.0) / 4.0) as f32 + 0.5;
     for plank in selected.iter() {
-        if let Some(mat) = mats.get_mut(plank.1.clone_weak()) {
-            mat.0.hilight_color = Color::rgba(0.2 * mult, 0.2 * mult, mult, 1.0);
+        if let Ok(mut mat) = mats.get_mut(plank.1) {
+            mat.hilight_color = Color::rgba(0.2 * mult, 0.2 * mult, mult, 1.0);
         }
     }
     for plank in targeted.iter() {
-        if let Some(mat) = mats.get_mut(plank.1.clone_weak()) {
-            mat.0.hilight_color = Color::rgba(0.5 * mult, 0.5 * mult, 0.5 * mult, 1.0);
+        if let Ok(mut mat) = mats.get_mut(plank.1) {
+            mat.hilight_color = Color::rgba(0.5 * mult, 0.5 * mult, 0.5 * mult, 1.0);
         }
     }
     for plank in neither.iter() {
-        if let Some(mat) = mats.get_mut(plank.1.clone_weak()) {
-            mat.0.hilight_color = Color::BLACK;
+        if let Ok(mut mat) = mats.get_mut(plank.1) {
+            mat.hilight_color = Color::BLACK;
         }
     }
 }
@@ -1147,6 +1112,7 @@ fn grab_or_drop(
                     .remove::<Targeted>()
                     .insert(Selected)
                     .insert(MoveSpeed(cursor_speed.0))
+                    .insert(AnalogPace::default())
                     .insert(Controller {
                         display_order: 4,
                         enabled: true,
@@ -1275,18 +1241,10 @@ fn rotate_plank(
             debug!("rot {}", dir);
 
             for _ in 0..dir {
-                plank.0.rotate();
+                plank.0.rotate_about(&mut plank_pos, cur_pos.0);
 
                 debug!("extents: {:?}", plank.0.extents());
-
-                let offset = cur_pos.0 - plank_pos.0;
-                let rotated = IVec2::new(-offset.y, offset.x);
-                debug!(
-                    "cur: {}, plank: {}, offset: {}",
-                    cur_pos.0, plank_pos.0, offset
-                );
-                plank_pos.0 = plank_pos.0 + offset - rotated;
-                debug!("rotated: {}, new pos: {}", rotated, plank_pos.0);
+                debug!("cur: {}, new pos: {}", cur_pos.0, plank_pos.0);
 
                 if let Some(child) = children.get(0) {
                     if let Ok(mut trans) = material_nodes.get_mut(*child) {
@@ -1318,69 +1276,6 @@ fn rotate_plank(
     }
 }
 
-#[derive(Component, Default)]
-struct Cut {
-    visited: HashSet<IVec2>,
-    separated: HashSet<(IVec2, IVec2)>,
-    finished: bool,
-}
-
-impl Cut {
-    fn split(&self, plank: &Plank) -> Option<[Plank; 2]> {
-        if self.separated.is_empty() {
-            return None;
-        }
-
-        let first = self.separated.iter().next().unwrap().0;
-        let mut connected = HashSet::new();
-        connected.insert(first);
-
-        let mut to_check = Vec::new();
-        to_check.push(first);
-
-        while !to_check.is_empty() {
-            let cur = to_check.pop().unwrap();
-            for n in [IVec2::X, IVec2::Y, -IVec2::X, -IVec2::Y].iter() {
-                let n = *n + cur;
-                if plank.contains(n)
-                    && !self.separated.contains(&(n.min(cur), n.max(cur)))
-                    && !connected.contains(&n)
-                {
-                    connected.insert(n);
-                    to_check.push(n);
-                }
-            }
-        }
-
-        if connected.len() != plank.count() {
-            let mut second = HashSet::new();
-            for item in plank.coords.iter() {
-                if !connected.contains(item) {
-                    second.insert(*item);
-                }
-            }
-            return Some([
-                Plank {
-                    coords: connected,
-                    turns: plank.turns,
-                    texture_offset: plank.texture_offset,
-                },
-                Plank {
-                    coords: second,
-                    turns: plank.turns,
-                    texture_offset: plank.texture_offset,
-                },
-            ]);
-        }
-
-        return None;
-    }
-
-    fn is_finished(&self, plank: &Plank) -> bool {
-        self.split(plank).is_some()
-    }
-}
-
 fn cut_plank(
     mut commands: Commands,
     mut ev: EventReader<ActionEvent>,
@@ -1433,28 +1328,9 @@ fn cut_plank(
                     // not cutting - begin
 
                     // spawn cutter
-                    let mut positions = Vec::new();
-
-                    // prefer last pos
-                    let last_offset = *last_cutter_pos - pos.0;
-                    if last_offset.max_element() <= 1 && last_offset.min_element() >= 0 {
-                        positions.push(*last_cutter_pos);
-                    }
-                    // then any nearby
-                    let offsets = [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE];
-                    for offset in offsets {
-                        positions.push(pos.0 + offset);
-                    }
+                    let valid = Cut::start_position(&plank.0, plank_pos.0, pos.0, *last_cutter_pos);
 
-                    let valid = positions.iter().find(|&&pos| {
-                        let count = offsets
-                            .iter()
-                            .filter(|&&n| plank.0.contains(pos + n - plank_pos.0 - IVec2::ONE))
-                            .count();
-                        count > 1 && count < 4
-                    });
-
-                    let Some(&valid) = valid else {
+                    let Some(valid) = valid else {
                         continue;
                     };
 
@@ -1481,6 +1357,7 @@ fn cut_plank(
                         .insert(Position(valid))
                         .insert(PrevPosition(valid))
                         .insert(MoveSpeed(cut_speed.0))
+                        .insert(AnalogPace::default())
                         .insert(ExtentItem(IVec2::ONE, IVec2::ONE))
                         .insert(Cut::default())
                         .insert(Controller {
@@ -1580,18 +1457,13 @@ fn cut_plank(
                         debug!("base");
                         debug_plank_mats(&base_plank.0);
 
-                        let planks = cut.split(&base_plank.0).unwrap();
+                        let planks = split_plank(cut, &base_plank.0, pos.0).unwrap();
                         commands.entity(selected_ent).despawn_recursive();
 
-                        for mut plank in planks.into_iter() {
-                            let shift =
-                                IVec2::new(-plank.extents().0 .0 + 1, -plank.extents().1 .0 + 1);
-                            plank.shift(shift);
-                            let pos = pos.0 - shift;
-
+                        for (plank, pos) in planks.into_iter() {
                             debug!(
-                                "shift: {}, base offset: {}, new offset: {}",
-                                shift, base_plank.0.texture_offset, plank.texture_offset
+                                "base offset: {}, new offset: {}",
+                                base_plank.0.texture_offset, plank.texture_offset
                             );
 
                             spawn_plank.send(SpawnPlank {
@@ -1614,6 +1486,7 @@ fn cut_plank(
                             }
                         }
                         commands.entity(cutter).despawn_recursive();
+                        end_cut.send(CutEvent::SplitPlank);
                         end_cut.send(CutEvent::CancelCut);
 
                         snap.send_default();
@@ -1701,9 +1574,14 @@ fn change_state(
     cutter: Query<&Cut>,
     asset_server: Res<AssetServer>,
     audio: Res<AudioChannel<UndoChannel>>,
+    mut stats: ResMut<LevelStats>,
 ) {
-    let Ok((&cursor_trans, mut cursor_pos)) = cursor.get_single_mut() else { return };
-    let Ok((&camera_trans, mut camera_pos, mut camera_pos_z)) = camera.get_single_mut() else { return };
+    let Ok((&cursor_trans, mut cursor_pos)) = cursor.get_single_mut() else {
+        return;
+    };
+    let Ok((&camera_trans, mut camera_pos, mut camera_pos_z)) = camera.get_single_mut() else {
+        return;
+    };
 
     let mut action_to_send = None;
 
@@ -1729,6 +1607,7 @@ fn change_state(
                 }
 
                 if let Some(state) = undo.prev() {
+                    stats.undos += 1;
                     audio
                         .play(asset_server.load(
                             "audio/zapsplat_sport_surfboard_leash_velcro_strap_undo_003.mp3",
@@ -1815,6 +1694,26 @@ enum CutEvent {
     CancelCut,
     FinishCut,
     UnfinishCut,
+    // a finished cut was made, splitting the plank
+    SplitPlank,
+}
+
+fn track_stats(
+    mut cut_evs: EventReader<CutEvent>,
+    mut stats: ResMut<LevelStats>,
+    controls: LevelControls,
+    time: Res<Time>,
+) {
+    for ev in cut_evs.iter() {
+        match ev {
+            CutEvent::SplitPlank => stats.cuts += 1,
+            _ => (),
+        }
+    }
+
+    if controls.enabled() {
+        stats.time += time.delta_seconds();
+    }
 }
 
 fn extend_cut(
@@ -1837,67 +1736,31 @@ fn extend_cut(
         }
 
         if let Ok((plank, plank_pos)) = selected.get_single() {
-            let dir = position.0 - prev.0;
-            let affected = match (dir.x, dir.y) {
-                (1, 0) => (prev.0 - IVec2::Y, prev.0),
-                (-1, 0) => (prev.0 - IVec2::ONE, prev.0 - IVec2::X),
-                (0, -1) => (prev.0 - IVec2::ONE, prev.0 - IVec2::Y),
-                (0, 1) => (prev.0 - IVec2::X, prev.0),
-                _ => {
-                    debug!("weird move, abort");
+            match cut.extend(&plank.0, plank_pos.0, prev.0, position.0) {
+                CutStep::Blocked => {
                     position.0 = prev.0;
                     trans.translation = position.0.as_vec2().extend(trans.translation.z);
                     continue;
                 }
-            };
-
-            let affected = (affected.0 - plank_pos.0, affected.1 - plank_pos.0);
-
-            if !plank.0.contains(affected.0) && !plank.0.contains(affected.1) {
-                debug!("air block");
-                position.0 = prev.0;
-                trans.translation = position.0.as_vec2().extend(trans.translation.z);
-                continue;
-            }
-
-            if cut.separated.contains(&affected) {
-                debug!("unchop");
-                cut.separated.remove(&affected);
-                cut.visited.remove(&prev.0);
-                cuts.send(CutEvent::UnCut {
-                    from: prev.0,
-                    to: position.0,
-                });
-                prev.0 = position.0;
-                if cut.finished {
-                    cuts.send(CutEvent::UnfinishCut);
-                    cut.finished = false;
+                CutStep::Moved => (),
+                CutStep::Chop { finished } => {
+                    cuts.send(CutEvent::NewCut {
+                        from: prev.0,
+                        to: position.0,
+                        speed: speed.0,
+                    });
+                    if finished {
+                        cuts.send(CutEvent::FinishCut);
+                    }
                 }
-                continue;
-            }
-
-            if cut.finished {
-                debug!("finished block");
-                position.0 = prev.0;
-                trans.translation = position.0.as_vec2().extend(trans.translation.z);
-                continue;
-            }
-
-            if plank.0.contains(affected.0) && plank.0.contains(affected.1) {
-                debug!("chop");
-                cut.visited.insert(prev.0);
-                cut.visited.insert(position.0);
-                cut.separated.insert(affected);
-                cuts.send(CutEvent::NewCut {
-                    from: prev.0,
-                    to: position.0,
-                    speed: speed.0,
-                });
-
-                if cut.is_finished(&plank.0) {
-                    debug!("finished!");
-                    cut.finished = true;
-                    cuts.send(CutEvent::FinishCut);
+                CutStep::UnChop { unfinished } => {
+                    cuts.send(CutEvent::UnCut {
+                        from: prev.0,
+                        to: position.0,
+                    });
+                    if unfinished {
+                        cuts.send(CutEvent::UnfinishCut);
+                    }
                 }
             }
 
@@ -1977,6 +1840,7 @@ fn draw_cuts(
                     commands.entity(*ent).insert(working.clone());
                 }
             }
+            CutEvent::SplitPlank => (),
         }
     }
 }
@@ -2157,13 +2021,7 @@ struct SpawnPlank {
     manual_extents: Option<IVec2>,
 }
 
-fn spawn_planks(
-    mut evs: EventReader<SpawnPlank>,
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut mats: ResMut<Assets<WoodMaterial>>,
-    mut images: ResMut<Assets<Image>>,
-) {
+fn spawn_planks(mut evs: EventReader<SpawnPlank>, mut commands: Commands) {
     for ev in evs.iter() {
         let mut plank = ev.plank.clone();
         let mut pos = ev.position;
@@ -2182,15 +2040,13 @@ fn spawn_planks(
             None => plank.size() + 2,
         };
 
-        let quad = BLQuad::new(size.as_vec2(), Vec2::ZERO);
-
         let colors = match (ev.is_plank, ev.is_interactive) {
             (true, true) => (1.5, 1.2, 1.0),
             (true, false) => (1.5, 1.2, 0.0),
             (false, _) => (0.8, 1.0, 0.0),
         };
 
-        let plank_spec = WoodMaterialSpec {
+        let wood = WoodMaterial {
             texture_offset: plank.texture_offset,
             turns: plank.turns,
             primary_color: Color::rgba(0.562, 0.272, 0.136, 1.0) * colors.0,
@@ -2198,13 +2054,12 @@ fn spawn_planks(
             hilight_color: Color::rgba(0.2, 0.2, 1.0, 1.0) * colors.2,
             size: size.as_uvec2(),
             is_plank: ev.is_plank,
-            base_color_texture: create_coordset_image(&mut images, &plank),
+            cells: plank.clone(),
         };
 
         debug!("plank offset: {}", plank.texture_offset);
 
-        let mat_handle = mats.add(SimpleTextureMaterial(plank_spec));
-        let cloned_mat = mat_handle.clone_weak();
+        let mut wood_ent = None;
         let mut cmds = commands.spawn();
 
         let z = match ev.is_plank {
@@ -2219,17 +2074,20 @@ fn spawn_planks(
             .insert(ExtentItem(IVec2::ZERO, size))
             .insert(pos)
             .with_children(|p| {
-                p.spawn_bundle(MaterialMeshBundle {
-                    mesh: meshes.add(quad.into()),
-                    material: mat_handle,
-                    ..Default::default()
-                });
+                wood_ent = Some(
+                    p.spawn()
+                        .insert(wood)
+                        .insert(Transform::default())
+                        .insert(GlobalTransform::default())
+                        .insert(Visibility::default())
+                        .id(),
+                );
             });
 
         if ev.is_plank {
             if ev.is_interactive {
                 // interactable
-                cmds.insert(PlankComponent(plank.clone(), cloned_mat));
+                cmds.insert(PlankComponent(plank.clone(), wood_ent.unwrap()));
             }
         } else {
             cmds.insert(MHoles);
@@ -2283,7 +2141,7 @@ fn hammer_home(
     mut commands: Commands,
     mut level: ResMut<Level>,
     mut done_planks: ResMut<DonePlanks>,
-    holes: Query<&Position, With<MHoles>>,
+    holes: Query<(), With<MHoles>>,
     target: Query<(Entity, &PlankComponent, &Position, &Transform), Without<Selected>>,
     mut menu: EventWriter<PopupMenuEvent>,
     levelset: Res<LevelSet>,
@@ -2292,94 +2150,158 @@ fn hammer_home(
     mut spawn_nails: EventWriter<SpawnNail>,
     mut snap: EventWriter<SnapUndo>,
     mut settings: ResMut<PkvStore>,
+    (def, stats): (Res<LevelDef>, Res<LevelStats>),
 ) {
-    let Ok(hole_pos) = holes.get_single() else {
+    if holes.is_empty() {
         return;
-    };
+    }
 
     let mut rng = thread_rng();
 
     for (plank_ent, plank, pos, trans) in target.iter() {
-        let mut shifted = plank.0.clone();
-        shifted.shift(pos.0 - hole_pos.0);
-        for (i, hole) in level.holes.holes.iter().enumerate() {
-            if shifted.equals(&hole) {
-                debug!("hammer!");
+        let Some(i) = level.matching_hole(&plank.0, *pos) else {
+            continue;
+        };
 
-                let mut new_trans = trans.clone();
-                new_trans.translation.z = PLANK_Z_DONE;
+        debug!("hammer!");
 
-                commands
-                    .entity(plank_ent)
-                    .remove::<PlankComponent>()
-                    .remove::<Targeted>()
-                    .remove::<Selected>()
-                    .remove::<Controller>()
-                    .insert(new_trans);
-                level.holes.holes.remove(i);
+        let mut new_trans = trans.clone();
+        new_trans.translation.z = PLANK_Z_DONE;
 
-                let max = 2.max(shifted.count() / 2);
-                shifted.shift(hole_pos.0);
+        commands
+            .entity(plank_ent)
+            .remove::<PlankComponent>()
+            .remove::<Targeted>()
+            .remove::<Selected>()
+            .remove::<Controller>()
+            .insert(new_trans);
+        level.holes.holes.remove(i);
 
-                let mut coords = shifted.coords.iter().collect::<Vec<_>>();
-                coords.shuffle(&mut rng);
+        let mut shifted = plank.0.clone();
+        shifted.shift(pos.0);
+        let max = 2.max(shifted.count() / 2);
 
-                audio.set_playback_rate(rng.gen_range(1.0..1.5));
-                audio.play(asset_server.load("audio/aaj_0404_HamrNail4Hits.mp3"));
+        let mut coords = shifted.coords.iter().collect::<Vec<_>>();
+        coords.shuffle(&mut rng);
 
-                let mut nails = Vec::new();
-                for coord in coords.into_iter().take(rng.gen_range(2..=max)) {
-                    spawn_nails.send(SpawnNail(*coord));
-                    nails.push(*coord);
-                }
+        audio.set_playback_rate(rng.gen_range(1.0..1.5));
+        audio.play(asset_server.load("audio/aaj_0404_HamrNail4Hits.mp3"));
 
-                done_planks.0.push((plank.0.clone(), *pos, nails));
+        let mut nails = Vec::new();
+        for coord in coords.into_iter().take(rng.gen_range(2..=max)) {
+            spawn_nails.send(SpawnNail(coord));
+            nails.push(coord);
+        }
 
-                if level.holes.holes.is_empty() {
-                    debug!("you win!");
+        done_planks.0.push((plank.0.clone(), *pos, nails));
 
-                    if let Ok(current) = settings.get(levelset.settings_key) {
-                        settings
-                            .set(
-                                levelset.settings_key,
-                                &29usize.min(levelset.current_level + 1).max(current),
-                            )
-                            .unwrap();
-                    }
+        if level.is_won() {
+            debug!("you win!");
 
-                    let mut items = vec![
-                        ("Restart Level".into(), ActionLabel("restart"), true),
-                        ("Main Menu".into(), ActionLabel("main menu"), true),
-                        (
-                            "Quit to Desktop".into(),
-                            ActionLabel("quit"),
-                            QUIT_TO_DESKTOP,
-                        ),
-                    ];
+            if let Ok(current) = settings.get(&levelset.settings_key) {
+                settings
+                    .set(
+                        &levelset.settings_key,
+                        &(levelset.levels.len() - 1)
+                            .min(levelset.current_level + 1)
+                            .max(current),
+                    )
+                    .unwrap();
+            }
+
+            let best_key = LevelStats::best_key(&levelset.settings_key, &def);
+            let best = settings.get::<LevelStats>(&best_key).ok();
+            let new_best = best.is_none_or(|best| stats.is_better_than(&best));
+            if new_best {
+                settings.set(&best_key, &*stats).unwrap();
+            }
 
-                    let next = levelset.current_level + 1;
+            let rank = leaderboard::record(&mut settings, &levelset, &def, &stats);
 
+            if resume::is_resumable(&levelset) {
+                resume::clear_saved_level(&mut settings);
+            }
+
+            let mut items = vec![
+                ("Restart Level".into(), ActionLabel("restart"), true),
+                ("Watch Replay".into(), ActionLabel("watch replay"), true),
+                ("Leaderboard".into(), ActionLabel("leaderboard"), true),
+                ("Main Menu".into(), ActionLabel("main menu"), true),
+                (
+                    "Quit to Desktop".into(),
+                    ActionLabel("quit"),
+                    QUIT_TO_DESKTOP,
+                ),
+            ];
+
+            if levelset.settings_key == EDITOR_KEY {
+                items.insert(0, ("Back to Editor".into(), ActionLabel("editor"), true));
+            }
+
+            let next = levelset.current_level + 1;
+            let progress = match levelset.settings_key.as_ref() {
+                ENDLESS_KEY => {
+                    let best = endless::record_run(&mut settings, next);
+                    format!("Endless run: {} (best {})", next, best)
+                }
+                _ => format!("{}/{} completed!", next, levelset.levels.len()),
+            };
+            let progress = match daily::set_date(&levelset) {
+                Some(date) if next == levelset.levels.len() => {
+                    let streak = daily::complete(&mut settings, date);
                     items.insert(
                         0,
-                        ("Next Level".into(), ActionLabel("next level"), next < 30),
+                        ("Daily Summary".into(), ActionLabel("daily summary"), true),
                     );
-
-                    menu.send(PopupMenuEvent {
-                        sender: Entity::from_raw(0),
-                        menu: PopupMenu {
-                            heading: format!("Nice one!\n {}/{} completed!", next, 30),
-                            items,
-                            ..Default::default()
-                        },
-                        sound: true,
-                    });
-                } else {
-                    snap.send_default();
+                    format!(
+                        "{}\ndaily streak {} (best {})",
+                        progress,
+                        streak.current_on(daily::today()),
+                        streak.best
+                    )
                 }
+                _ => progress,
+            };
 
-                return;
-            }
+            items.insert(
+                0,
+                (
+                    "Next Level".into(),
+                    ActionLabel("next level"),
+                    next < levelset.levels.len(),
+                ),
+            );
+
+            menu.send(PopupMenuEvent {
+                sender: Entity::from_raw(0),
+                menu: PopupMenu {
+                    heading: format!(
+                        "Nice one!\n {}\n{} / 3 stars{}{}\n{} cuts (par {}), {} undos, {:.0}s",
+                        progress,
+                        stats.stars(),
+                        match (new_best, best) {
+                            (true, Some(_)) => " - new best!",
+                            _ => "",
+                        },
+                        match rank {
+                            Some(rank) => format!(" - #{} on the leaderboard", rank + 1),
+                            None => String::new(),
+                        },
+                        stats.cuts,
+                        stats.par,
+                        stats.undos,
+                        stats.time,
+                    ),
+                    items,
+                    ..Default::default()
+                },
+                sound: true,
+            });
+        } else {
+            snap.send_default();
         }
+
+        return;
     }
 }
 
@@ -2415,8 +2337,24 @@ fn check_cut_actions(
         (&mut Controller, &Cut),
         (With<Cut>, Without<Cursor>, Without<SystemController>),
     >,
-    mut cursor: Query<&mut Controller, (Without<Cut>, With<Cursor>, Without<SystemController>)>,
-    mut system: Query<&mut Controller, (Without<Cut>, Without<Cursor>, With<SystemController>)>,
+    mut cursor: Query<
+        &mut Controller,
+        (
+            Without<Cut>,
+            With<Cursor>,
+            Without<SystemController>,
+            Without<EditorControls>,
+        ),
+    >,
+    mut system: Query<
+        &mut Controller,
+        (
+            Without<Cut>,
+            Without<Cursor>,
+            With<SystemController>,
+            Without<EditorControls>,
+        ),
+    >,
     undo: Res<UndoBuffer>,
     select: Query<(), With<Selected>>,
     target: Query<(), With<Targeted>>,
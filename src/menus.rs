@@ -3,18 +3,28 @@ use bevy::{
     prelude::*,
     utils::HashMap,
 };
-use bevy_egui::{egui, EguiContext};
+use bevy_carp::{
+    level_file::LevelPack,
+    levels::{endless_set, pack_set, spawn_random, MAX_BLOCKS, MAX_HOLES, MAX_SET_TOTAL},
+    model::{LevelBase, Plank},
+    share::ShareCode,
+};
+use bevy_egui::{egui, EguiClipboard, EguiContext};
 use bevy_kira_audio::AudioChannel;
 use bevy_pkv::PkvStore;
 use egui_extras::StripBuilder;
 
 use crate::{
+    daily::{daily_set, today},
+    editor::EDITOR_KEY,
+    endless::{best_run, ENDLESS_KEY},
     input::{
         Action, ActionInputs, ActionType, Controller, DisplayMode, InputItem, NewInputController,
-        NewInputEvent,
+        NewInputEvent, StickResponse,
     },
-    model::{CoordSet, LevelBase},
-    spawn_random,
+    leaderboard::player_name,
+    packs::{LevelPacks, LEVEL_PACKS},
+    resume::has_saved_level,
     structs::{
         ActionEvent, ActionLabel, ChangeBackground, ControlHelp, Position, PositionZ,
         QUIT_TO_DESKTOP,
@@ -238,6 +248,7 @@ pub(crate) fn spawn_main_menu(
     server: Res<AssetServer>,
     mut handle: Local<Option<Handle<Image>>>,
     mut def: ResMut<LevelDef>,
+    settings: Res<PkvStore>,
 ) {
     let mut run = false;
 
@@ -257,7 +268,7 @@ pub(crate) fn spawn_main_menu(
 
     let Some(image) = images.get(&*handle) else {
         // try again next time
-        actions.send(ActionEvent{
+        actions.send(ActionEvent {
             sender: Entity::from_raw(0),
             label: ActionLabel("main menu"),
             target: None,
@@ -272,18 +283,7 @@ pub(crate) fn spawn_main_menu(
 
     bg.send_default();
 
-    let mut plank = CoordSet::default();
-    let width = image.size().x as usize;
-
-    for (i, word) in image.data.chunks(4).enumerate() {
-        if word.iter().any(|b| *b < 254) {
-            plank
-                .coords
-                .insert(IVec2::new((i % width) as i32, -((i / width) as i32)));
-        }
-    }
-
-    plank = plank.normalize();
+    let mut plank = Plank::from_pixels(&image.data, image.size().x as usize);
     plank.shift(IVec2::ONE);
     // let manual_extents = Some(image.size().as_ivec2());
 
@@ -313,19 +313,25 @@ pub(crate) fn spawn_main_menu(
         target: None,
     });
 
+    let mut items = vec![
+        ("Play".into(), ActionLabel("play"), true),
+        ("Level Editor".into(), ActionLabel("editor"), true),
+        ("Options".into(), ActionLabel("options"), true),
+        ("Credits".into(), ActionLabel("credits"), true),
+        (
+            "Quit to Desktop".into(),
+            ActionLabel("quit"),
+            QUIT_TO_DESKTOP,
+        ),
+    ];
+    if has_saved_level(&settings) {
+        items.insert(0, ("Continue".into(), ActionLabel("continue"), true));
+    }
+
     popup.send(PopupMenuEvent {
         sender: cam_id,
         menu: PopupMenu {
-            items: vec![
-                ("Play".into(), ActionLabel("play"), true),
-                ("Options".into(), ActionLabel("options"), true),
-                ("Credits".into(), ActionLabel("credits"), true),
-                (
-                    "Quit to Desktop".into(),
-                    ActionLabel("quit"),
-                    QUIT_TO_DESKTOP,
-                ),
-            ],
+            items,
             transparent: true,
             header_size: 0.4,
             footer: format!("v{}", env!("CARGO_PKG_VERSION")),
@@ -416,6 +422,57 @@ pub fn spawn_credits(mut ev: EventReader<ActionEvent>, mut menu: EventWriter<Pop
     }
 }
 
+// levels per page of the level select grid
+const LEVEL_PAGE_SIZE: usize = 30;
+
+/// action labels for picking a level, created as needed since sets can be any length
+#[derive(Default)]
+pub struct LevelLabels(Vec<&'static str>);
+
+impl LevelLabels {
+    fn get(&mut self, i: usize) -> ActionLabel {
+        while self.0.len() <= i {
+            let label = format!("select level {}", self.0.len());
+            self.0.push(Box::leak(label.into_boxed_str()));
+        }
+        ActionLabel(self.0[i])
+    }
+
+    fn index(&self, label: ActionLabel) -> Option<usize> {
+        self.0.iter().position(|l| *l == label.0)
+    }
+}
+
+fn level_select_menu(
+    levelset: &LevelSet,
+    max_level: usize,
+    page: usize,
+    labels: &mut LevelLabels,
+) -> PopupMenu {
+    let pages = levelset.levels.len().div_ceil(LEVEL_PAGE_SIZE);
+    let first = page * LEVEL_PAGE_SIZE;
+    let mut items: Vec<(MenuItem, ActionLabel, bool)> = (first..levelset.levels.len())
+        .take(LEVEL_PAGE_SIZE)
+        .map(|i| ((i + 1).to_string().into(), labels.get(i), i <= max_level))
+        .collect();
+
+    let mut heading = format!("{}\nSelect Level", levelset.title);
+    if pages > 1 {
+        heading = format!("{} ({}/{})", heading, page + 1, pages);
+        items.push(("<".into(), ActionLabel("prev level page"), page > 0));
+        items.push((">".into(), ActionLabel("next level page"), page + 1 < pages));
+    }
+    items.push(("Scores".into(), ActionLabel("level scores"), true));
+
+    PopupMenu {
+        heading,
+        items,
+        cancel_action: Some(ActionLabel("play")),
+        width: 6,
+        ..Default::default()
+    }
+}
+
 pub fn spawn_play_menu(
     evs: ResMut<Events<ActionEvent>>,
     mut reader: Local<ManualEventReader<ActionEvent>>,
@@ -423,39 +480,48 @@ pub fn spawn_play_menu(
     mut spawn_level: EventWriter<SpawnLevelEvent>,
     mut levelset: ResMut<LevelSet>,
     mut settings: ResMut<PkvStore>,
+    packs: Res<LevelPacks>,
+    pack_assets: Res<Assets<LevelPack>>,
+    mut labels: Local<LevelLabels>,
+    mut page: Local<usize>,
 ) {
-    let today = chrono::Utc::today().naive_utc();
-    let start_date = chrono::NaiveDate::from_ymd(2022, 6, 1);
-
-    // this sucks i know
-    let strs = [
-        "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15", "16",
-        "17", "18", "19", "20", "21", "22", "23", "24", "25", "26", "27", "28", "29",
-    ];
-
     for ev in reader.iter(&evs) {
-        let key;
-        for i in 0..30 {
-            if ev.label == ActionLabel(strs[i]) {
+        if let Some(i) = labels.index(ev.label) {
+            if i < levelset.levels.len() {
                 levelset.current_level = i;
                 spawn_level.send(SpawnLevelEvent {
                     def: levelset.levels[i].clone(),
                 });
             }
+            continue;
         }
 
         match ev.label.0 {
             "play" => {
+                let mut items = vec![
+                    ("Easy".into(), ActionLabel("play easy"), true),
+                    ("Medium".into(), ActionLabel("play medium"), true),
+                    ("Hard".into(), ActionLabel("play hard"), true),
+                    ("Daily Mix".into(), ActionLabel("play daily"), true),
+                    ("Daily Archive".into(), ActionLabel("daily archive"), true),
+                    ("Custom".into(), ActionLabel("custom"), true),
+                    ("Paste Code".into(), ActionLabel("paste code"), true),
+                    (
+                        format!("Endless (best {})", best_run(&settings)).into(),
+                        ActionLabel("play endless"),
+                        true,
+                    ),
+                ];
+                for ((_, label, _), handle) in LEVEL_PACKS.iter().zip(packs.0.iter()) {
+                    if let Some(pack) = pack_assets.get(handle) {
+                        items.push((pack.title.clone().into(), ActionLabel(label), true));
+                    }
+                }
                 spawn_menu.send(PopupMenuEvent {
                     sender: ev.sender,
                     menu: PopupMenu {
                         heading: "Choose Difficulty".into(),
-                        items: vec![
-                            ("Easy".into(), ActionLabel("play easy"), true),
-                            ("Medium".into(), ActionLabel("play medium"), true),
-                            ("Hard".into(), ActionLabel("play hard"), true),
-                            ("Daily Mix".into(), ActionLabel("play daily"), true),
-                        ],
+                        items,
                         cancel_action: Some(ActionLabel("main menu")),
                         ..Default::default()
                     },
@@ -463,37 +529,64 @@ pub fn spawn_play_menu(
                 });
                 return;
             }
+            "prev level page" | "next level page" => {
+                *page = match ev.label.0 {
+                    "prev level page" => page.saturating_sub(1),
+                    _ => *page + 1,
+                };
+                let max_level: usize = settings.get(&levelset.settings_key).unwrap_or_default();
+                spawn_menu.send(PopupMenuEvent {
+                    menu: level_select_menu(&levelset, max_level, *page, &mut labels),
+                    sender: Entity::from_raw(0),
+                    sound: false,
+                });
+                continue;
+            }
+            // the fixed sets stay on the first generator so saved progress still matches
             "play easy" => {
-                key = "Easy";
-                *levelset = spawn_random(90, 0, "Easy Set".into(), 11, key);
+                *levelset = spawn_random(MAX_SET_TOTAL, 0, 30, "Easy Set".into(), 11, 0, "Easy");
             }
             "play medium" => {
-                key = "Medium";
-                *levelset = spawn_random(90, 30, "Medium Set".into(), 15, key);
+                *levelset =
+                    spawn_random(MAX_SET_TOTAL, 30, 30, "Medium Set".into(), 15, 0, "Medium");
             }
             "play hard" => {
-                key = "Hard";
-                *levelset = spawn_random(90, 60, "Hard Set".into(), 15, key);
+                *levelset = spawn_random(MAX_SET_TOTAL, 60, 30, "Hard Set".into(), 15, 0, "Hard");
             }
             "play daily" => {
-                let dur = today.signed_duration_since(start_date);
-                let seed = dur.num_days() * 1068;
-                key = "Daily";
-                *levelset =
-                    spawn_random(30, 0, format!("Daily Set for {}", today), seed as u64, key);
+                *levelset = daily_set(today(), &mut settings);
+            }
+            // chosen elsewhere, e.g. the daily archive
+            "play current set" => (),
+            "play endless" => {
+                // a new run every time, there's no level select
+                *levelset = endless_set(chrono::Utc::now().timestamp() as u64, ENDLESS_KEY);
+                spawn_level.send(SpawnLevelEvent {
+                    def: levelset.levels[0].clone(),
+                });
+                continue;
+            }
+            label => {
+                let pack = LEVEL_PACKS
+                    .iter()
+                    .zip(packs.0.iter())
+                    .find(|((_, pack_label, _), _)| *pack_label == label);
+                let Some(((pack_key, _, _), handle)) = pack else {
+                    return;
+                };
+                let Some(pack) = pack_assets.get(handle) else {
+                    return;
+                };
+                *levelset = pack_set(pack, pack_key);
             }
-            _ => return,
         }
 
-        if key == "Daily" {
-            let current_daily = settings.get("current daily date").unwrap_or(start_date);
-            if current_daily != today {
-                settings.set(key, &0usize).unwrap();
-                settings.set("current daily date", &today).unwrap();
-            }
+        if levelset.levels.is_empty() {
+            warn!("level set {} is empty", levelset.title);
+            return;
         }
 
-        let max_level: usize = settings.get(key).unwrap_or_default();
+        let max_level: usize = settings.get(&levelset.settings_key).unwrap_or_default();
         if max_level == 0 {
             spawn_level.send(SpawnLevelEvent {
                 def: levelset.levels[0].clone(),
@@ -501,27 +594,11 @@ pub fn spawn_play_menu(
             return;
         }
 
-        // if we get here we must have chosen a set, and already started the set
-        let items = (0..30)
-            .map(|i| {
-                (
-                    (i + 1).to_string().into(),
-                    ActionLabel(strs[i]),
-                    i <= max_level,
-                )
-            })
-            .collect();
-
-        let menu = PopupMenu {
-            heading: format!("{}\nSelect Level", levelset.title),
-            items,
-            cancel_action: Some(ActionLabel("play")),
-            width: 6,
-            ..Default::default()
-        };
-
+        // if we get here we must have chosen a set, and already started the set.
+        // open on the page with the furthest unlocked level
+        *page = max_level.min(levelset.levels.len() - 1) / LEVEL_PAGE_SIZE;
         spawn_menu.send(PopupMenuEvent {
-            menu,
+            menu: level_select_menu(&levelset, max_level, *page, &mut labels),
             sender: Entity::from_raw(0),
             sound: false,
         });
@@ -542,20 +619,38 @@ pub fn spawn_in_level_menu(
                 level.num_holes, level.total_blocks, level.seed
             );
             debug!("difficulty: {}", base.0.difficulty());
+            let mut items = vec![
+                ("Resume".into(), ActionLabel("cancel"), true),
+                ("Restart Level".into(), ActionLabel("restart"), true),
+                ("Save Replay".into(), ActionLabel("save replay"), true),
+                (
+                    "Share".into(),
+                    ActionLabel("share"),
+                    level.file.is_none() || set.recipe.is_some(),
+                ),
+                ("Main Menu".into(), ActionLabel("main menu"), true),
+                (
+                    "Quit to Desktop".into(),
+                    ActionLabel("quit"),
+                    QUIT_TO_DESKTOP,
+                ),
+            ];
+            if set.settings_key == EDITOR_KEY {
+                items.insert(4, ("Back to Editor".into(), ActionLabel("editor"), true));
+            }
             spawn.send(PopupMenuEvent {
                 sender: ev.sender,
                 menu: PopupMenu {
-                    heading: format!("Paused ({}/{})\n{}", set.current_level + 1, 30, set.title,),
-                    items: vec![
-                        ("Resume".into(), ActionLabel("cancel"), true),
-                        ("Restart Level".into(), ActionLabel("restart"), true),
-                        ("Main Menu".into(), ActionLabel("main menu"), true),
-                        (
-                            "Quit to Desktop".into(),
-                            ActionLabel("quit"),
-                            QUIT_TO_DESKTOP,
+                    heading: match set.settings_key.as_ref() {
+                        ENDLESS_KEY => format!("Paused ({})\n{}", set.current_level + 1, set.title),
+                        _ => format!(
+                            "Paused ({}/{})\n{}",
+                            set.current_level + 1,
+                            set.levels.len(),
+                            set.title,
                         ),
-                    ],
+                    },
+                    items,
                     cancel_action: Some(ActionLabel("cancel")),
                     ..Default::default()
                 },
@@ -565,6 +660,116 @@ pub fn spawn_in_level_menu(
     }
 }
 
+const SHARED_KEY: &str = "Shared";
+
+// the codes for the current level and set, where they were generated
+fn share_codes(level: &LevelDef, levelset: &LevelSet) -> (Option<String>, Option<String>) {
+    let level_code = match level.file {
+        None => Some(ShareCode::Level(level.clone()).encode()),
+        Some(_) => None,
+    };
+    let set_code = levelset
+        .recipe
+        .as_ref()
+        .map(|recipe| ShareCode::Set(recipe.clone()).encode());
+    (level_code, set_code)
+}
+
+pub fn spawn_share_menu(
+    mut evs: EventReader<ActionEvent>,
+    mut spawn: EventWriter<PopupMenuEvent>,
+    mut spawn_level: EventWriter<SpawnLevelEvent>,
+    mut levelset: ResMut<LevelSet>,
+    level: Res<LevelDef>,
+    mut clipboard: ResMut<EguiClipboard>,
+) {
+    for ev in evs.iter() {
+        let (heading, back) = match ev.label.0 {
+            "share" => {
+                let (level_code, set_code) = share_codes(&level, &levelset);
+                let mut heading = "Share".to_string();
+                if let Some(code) = level_code.as_ref() {
+                    heading = format!("{}\nLevel: {}", heading, code);
+                }
+                if let Some(code) = set_code.as_ref() {
+                    heading = format!("{}\nSet: {}", heading, code);
+                }
+                spawn.send(PopupMenuEvent {
+                    sender: ev.sender,
+                    menu: PopupMenu {
+                        heading,
+                        items: vec![
+                            (
+                                "Copy Level Code".into(),
+                                ActionLabel("copy level code"),
+                                level_code.is_some(),
+                            ),
+                            (
+                                "Copy Set Code".into(),
+                                ActionLabel("copy set code"),
+                                set_code.is_some(),
+                            ),
+                            ("Back".into(), ActionLabel("pause"), true),
+                        ],
+                        cancel_action: Some(ActionLabel("pause")),
+                        ..Default::default()
+                    },
+                    sound: false,
+                });
+                continue;
+            }
+            "copy level code" | "copy set code" => {
+                let (level_code, set_code) = share_codes(&level, &levelset);
+                let code = match ev.label.0 {
+                    "copy level code" => level_code,
+                    _ => set_code,
+                };
+                let Some(code) = code else {
+                    continue;
+                };
+                clipboard.set_contents(&code);
+                (format!("Copied\n{}", code), "pause")
+            }
+            "paste code" => {
+                let code = clipboard.get_contents().unwrap_or_default();
+                match ShareCode::decode(&code) {
+                    Ok(ShareCode::Level(def)) => {
+                        *levelset = LevelSet {
+                            levels: vec![def.clone()],
+                            current_level: 0,
+                            title: "Shared Level".into(),
+                            settings_key: SHARED_KEY.into(),
+                            recipe: None,
+                        };
+                        spawn_level.send(SpawnLevelEvent { def });
+                        continue;
+                    }
+                    Ok(ShareCode::Set(recipe)) => {
+                        *levelset = recipe.build(SHARED_KEY);
+                        spawn_level.send(SpawnLevelEvent {
+                            def: levelset.levels[0].clone(),
+                        });
+                        continue;
+                    }
+                    Err(e) => (format!("Copy a level code first\n{}", e), "play"),
+                }
+            }
+            _ => continue,
+        };
+
+        spawn.send(PopupMenuEvent {
+            sender: ev.sender,
+            menu: PopupMenu {
+                heading,
+                items: vec![("Ok".into(), ActionLabel(back), true)],
+                cancel_action: Some(ActionLabel(back)),
+                ..Default::default()
+            },
+            sound: false,
+        });
+    }
+}
+
 pub fn spawn_popup_menu(
     mut commands: Commands,
     mut other_controllers: Query<(Entity, &mut Controller), Without<MenuMarker>>,
@@ -731,6 +936,10 @@ pub fn spawn_popup_menu(
         }
     }
 
+    // items under the pointer, when it moved and when it clicked
+    let mut hovered = None;
+    let mut clicked = None;
+
     if let Some((menu, _)) = active_menu.as_ref() {
         let fill: egui::Color32 = match menu.transparent {
             true => egui::Rgba::from_rgba_premultiplied(0.0, 0.0, 0.0, 0.0).into(),
@@ -771,6 +980,7 @@ pub fn spawn_popup_menu(
                                             .into(),
                                     );
                                     text.render(ui, menu.text_size, color, bg);
+                                    point_at(ui, i, *enabled, &mut hovered, &mut clicked);
                                 });
                             }
                         } else {
@@ -795,6 +1005,13 @@ pub fn spawn_popup_menu(
                                                         .into(),
                                                     );
                                                     text.render(ui, menu.text_size, color, bg);
+                                                    point_at(
+                                                        ui,
+                                                        pos,
+                                                        *enabled,
+                                                        &mut hovered,
+                                                        &mut clicked,
+                                                    );
                                                 });
                                             }
                                         });
@@ -812,6 +1029,46 @@ pub fn spawn_popup_menu(
             });
     }
 
+    if let Some((menu, _)) = active_menu.as_ref() {
+        if egui_context.ctx_mut().input().pointer.delta() == egui::Vec2::ZERO {
+            hovered = None;
+        }
+        match menu.modal_controller {
+            None => {
+                if let Some(i) = clicked.or(hovered) {
+                    *menu_position = i;
+                }
+                if let (Some(_), Some(menu_ent)) = (clicked, menu_items.iter().next()) {
+                    actions.send(ActionEvent {
+                        sender: menu_ent,
+                        label: ActionLabel("select"),
+                        target: None,
+                    });
+                }
+            }
+            // a click finishes whatever the modal controller is doing
+            Some(modal) => {
+                let main_action = other_controllers
+                    .get(modal)
+                    .ok()
+                    .and_then(|(_, controller)| {
+                        controller
+                            .actions
+                            .iter()
+                            .find(|(ty, _)| *ty == ActionType::MainAction)
+                            .map(|(_, action)| action.label)
+                    });
+                if let (Some(_), Some(label)) = (clicked, main_action) {
+                    actions.send(ActionEvent {
+                        sender: modal,
+                        label,
+                        target: None,
+                    });
+                }
+            }
+        }
+    }
+
     let mut to_send = None;
 
     for ev in action_reader.iter(&actions) {
@@ -933,6 +1190,91 @@ pub fn spawn_popup_menu(
     }
 }
 
+// note an enabled item the pointer is over or clicked on
+fn point_at(
+    ui: &mut egui::Ui,
+    i: usize,
+    enabled: bool,
+    hovered: &mut Option<usize>,
+    clicked: &mut Option<usize>,
+) {
+    if !enabled {
+        return;
+    }
+    let response = ui.interact(ui.max_rect(), ui.id().with(i), egui::Sense::click());
+    if response.hovered() {
+        *hovered = Some(i);
+    }
+    if response.clicked() {
+        *clicked = Some(i);
+    }
+}
+
+// left / right adjusts a slider, main or second action finishes
+fn slide_controller(left: &'static str, right: &'static str) -> Controller {
+    Controller {
+        display_order: 5,
+        actions: vec![
+            (
+                ActionType::MoveLeft,
+                Action {
+                    label: ActionLabel(left),
+                    sticky: false,
+                    display: DisplayMode::Active,
+                    display_text: None,
+                },
+            ),
+            (
+                ActionType::PanLeft,
+                Action {
+                    label: ActionLabel(left),
+                    sticky: false,
+                    display: DisplayMode::Off,
+                    display_text: None,
+                },
+            ),
+            (
+                ActionType::MoveRight,
+                Action {
+                    label: ActionLabel(right),
+                    sticky: false,
+                    display: DisplayMode::Active,
+                    display_text: None,
+                },
+            ),
+            (
+                ActionType::PanRight,
+                Action {
+                    label: ActionLabel(right),
+                    sticky: false,
+                    display: DisplayMode::Off,
+                    display_text: None,
+                },
+            ),
+            (
+                ActionType::MainAction,
+                Action {
+                    label: ActionLabel("done"),
+                    sticky: true,
+                    display: DisplayMode::Active,
+                    display_text: None,
+                },
+            ),
+            (
+                ActionType::SecondAction,
+                Action {
+                    label: ActionLabel("done"),
+                    sticky: true,
+                    display: DisplayMode::Off,
+                    display_text: None,
+                },
+            ),
+        ],
+        enabled: true,
+        ..Default::default()
+    }
+}
+
 pub fn spawn_options_menu(
     mut commands: Commands,
     mut evs: ResMut<Events<ActionEvent>>,
@@ -943,6 +1285,7 @@ pub fn spawn_options_menu(
     mut control_help: ResMut<ControlHelp>,
     mut cursor_speed: ResMut<CursorSpeed>,
     mut cutter_speed: ResMut<CutSpeed>,
+    mut stick: ResMut<StickResponse>,
     mut music: ResMut<MusicVolume>,
     mut sfx: ResMut<SfxVolume>,
     mut sender: Local<EntityMarker>,
@@ -952,70 +1295,6 @@ pub fn spawn_options_menu(
         *sender = commands.spawn().id().into();
     }
 
-    let slide_controller = |left: &'static str, right: &'static str| -> Controller {
-        Controller {
-            display_order: 5,
-            actions: vec![
-                (
-                    ActionType::MoveLeft,
-                    Action {
-                        label: ActionLabel(left),
-                        sticky: false,
-                        display: DisplayMode::Active,
-                        display_text: None,
-                    },
-                ),
-                (
-                    ActionType::PanLeft,
-                    Action {
-                        label: ActionLabel(left),
-                        sticky: false,
-                        display: DisplayMode::Off,
-                        display_text: None,
-                    },
-                ),
-                (
-                    ActionType::MoveRight,
-                    Action {
-                        label: ActionLabel(right),
-                        sticky: false,
-                        display: DisplayMode::Active,
-                        display_text: None,
-                    },
-                ),
-                (
-                    ActionType::PanRight,
-                    Action {
-                        label: ActionLabel(right),
-                        sticky: false,
-                        display: DisplayMode::Off,
-                        display_text: None,
-                    },
-                ),
-                (
-                    ActionType::MainAction,
-                    Action {
-                        label: ActionLabel("done"),
-                        sticky: true,
-                        display: DisplayMode::Active,
-                        display_text: None,
-                    },
-                ),
-                (
-                    ActionType::SecondAction,
-                    Action {
-                        label: ActionLabel("done"),
-                        sticky: true,
-                        display: DisplayMode::Off,
-                        display_text: None,
-                    },
-                ),
-            ],
-            enabled: true,
-            ..Default::default()
-        }
-    };
-
     let mut to_send;
     let mut modal_entity = None;
     let mut keep_position = false;
@@ -1075,11 +1354,27 @@ pub fn spawn_options_menu(
                                     ActionLabel("cutter speed"),
                                     true,
                                 ),
+                                ("Stick Dead Zone".into(), ActionLabel(""), false),
+                                (
+                                    MenuItem::Slider((stick.dead_zone * 200.0) as i32, 100),
+                                    ActionLabel("dead zone"),
+                                    true,
+                                ),
+                                ("Stick Curve".into(), ActionLabel(""), false),
+                                (
+                                    MenuItem::Slider(((stick.curve - 1.0) * 50.0) as i32, 100),
+                                    ActionLabel("stick curve"),
+                                    true,
+                                ),
+                                ("Player Name".into(), ActionLabel(""), false),
+                                (
+                                    player_name(&settings).into(),
+                                    ActionLabel("edit name"),
+                                    true,
+                                ),
                                 ("Controls".into(), ActionLabel(""), false),
                                 ("Edit".into(), ActionLabel("controls"), true),
                                 ("".into(), ActionLabel(""), false),
-                                ("".into(), ActionLabel(""), false),
-                                ("".into(), ActionLabel(""), false),
                                 ("Ok".into(), ActionLabel("main menu"), true),
                             ],
                             cancel_action: Some(ActionLabel("main menu")),
@@ -1151,6 +1446,22 @@ pub fn spawn_options_menu(
                         modal_entity = Some(controller);
                         to_send = Some("options");
                     }
+                    "dead zone" => {
+                        let controller = commands
+                            .spawn()
+                            .insert(slide_controller("smaller dead zone", "bigger dead zone"))
+                            .id();
+                        modal_entity = Some(controller);
+                        to_send = Some("options");
+                    }
+                    "stick curve" => {
+                        let controller = commands
+                            .spawn()
+                            .insert(slide_controller("flatter curve", "steeper curve"))
+                            .id();
+                        modal_entity = Some(controller);
+                        to_send = Some("options");
+                    }
                     _ => (),
                 }
             }
@@ -1198,6 +1509,26 @@ pub fn spawn_options_menu(
                         modal_entity = Some(ev.sender);
                         to_send = Some("options")
                     }
+                    "bigger dead zone" => {
+                        stick.dead_zone = f32::min(0.5, stick.dead_zone + 0.005);
+                        modal_entity = Some(ev.sender);
+                        to_send = Some("options")
+                    }
+                    "smaller dead zone" => {
+                        stick.dead_zone = f32::max(0.05, stick.dead_zone - 0.005);
+                        modal_entity = Some(ev.sender);
+                        to_send = Some("options")
+                    }
+                    "steeper curve" => {
+                        stick.curve = f32::min(3.0, stick.curve + 0.02);
+                        modal_entity = Some(ev.sender);
+                        to_send = Some("options")
+                    }
+                    "flatter curve" => {
+                        stick.curve = f32::max(1.0, stick.curve - 0.02);
+                        modal_entity = Some(ev.sender);
+                        to_send = Some("options")
+                    }
                     "done" => {
                         commands.entity(ev.sender).despawn_recursive();
                         to_send = Some("options");
@@ -1218,6 +1549,172 @@ pub fn spawn_options_menu(
     }
 }
 
+const CUSTOM_KEY: &str = "Custom";
+const CUSTOM_SETTINGS_KEY: &str = "custom level";
+
+pub fn spawn_custom_menu(
+    mut commands: Commands,
+    mut evs: ResMut<Events<ActionEvent>>,
+    mut reader: Local<ManualEventReader<ActionEvent>>,
+    mut spawn: EventWriter<PopupMenuEvent>,
+    mut spawn_level: EventWriter<SpawnLevelEvent>,
+    mut levelset: ResMut<LevelSet>,
+    mut settings: ResMut<PkvStore>,
+    mut def: Local<Option<LevelDef>>,
+    mut sender: Local<EntityMarker>,
+    mut modal_sender: Local<EntityMarker>,
+) {
+    if matches!(*sender, EntityMarker::None) {
+        *sender = commands.spawn().id().into();
+    }
+
+    // the last custom level, or the first easy level's numbers
+    let def = def.get_or_insert_with(|| {
+        settings
+            .get::<LevelDef>(CUSTOM_SETTINGS_KEY)
+            .unwrap_or(LevelDef {
+                num_holes: 3,
+                total_blocks: 12,
+                seed: 0,
+                file: None,
+            })
+    });
+
+    let mut to_send;
+    let mut modal_entity = None;
+    let mut keep_position = false;
+
+    while !reader.is_empty(&evs) {
+        to_send = None;
+        for ev in reader.iter(&evs) {
+            // any sender
+            if ev.label.0 == "custom" {
+                let check = def.check_generator();
+                let heading = match check.as_ref() {
+                    Ok(()) => format!(
+                        "Custom Level\n{} holes, {} blocks",
+                        def.num_holes, def.total_blocks
+                    ),
+                    Err(e) => format!(
+                        "Custom Level\n{} holes, {} blocks - {}",
+                        def.num_holes, def.total_blocks, e
+                    ),
+                };
+
+                spawn.send(PopupMenuEvent {
+                    sender: sender.entity(),
+                    menu: PopupMenu {
+                        heading,
+                        items: vec![
+                            ("Holes".into(), ActionLabel(""), false),
+                            (
+                                MenuItem::Slider(def.num_holes as i32, MAX_HOLES as i32),
+                                ActionLabel("custom holes"),
+                                true,
+                            ),
+                            ("Blocks".into(), ActionLabel(""), false),
+                            (
+                                MenuItem::Slider(def.total_blocks as i32, MAX_BLOCKS as i32),
+                                ActionLabel("custom blocks"),
+                                true,
+                            ),
+                            ("Seed".into(), ActionLabel(""), false),
+                            (
+                                def.seed.to_string().into(),
+                                ActionLabel("custom seed"),
+                                true,
+                            ),
+                            ("".into(), ActionLabel(""), false),
+                            (
+                                "Random Seed".into(),
+                                ActionLabel("custom random seed"),
+                                true,
+                            ),
+                            ("Back".into(), ActionLabel("play"), true),
+                            ("Play".into(), ActionLabel("custom play"), check.is_ok()),
+                        ],
+                        cancel_action: Some(ActionLabel("play")),
+                        width: 2,
+                        initial_position: if keep_position { -1 } else { 0 },
+                        inactive_color: egui::Color32::from_rgb(255, 255, 255),
+                        text_size: 30.0,
+                        modal_controller: modal_entity,
+                        ..Default::default()
+                    },
+                    sound: false,
+                });
+
+                *modal_sender = modal_entity.into();
+            }
+
+            // just our top-level sender
+            if sender.matches(&ev.sender) {
+                let slider = match ev.label.0 {
+                    "custom holes" => Some(slide_controller("fewer holes", "more holes")),
+                    "custom blocks" => Some(slide_controller("fewer blocks", "more blocks")),
+                    "custom seed" => Some(slide_controller("prev seed", "next seed")),
+                    _ => None,
+                };
+                if let Some(slider) = slider {
+                    modal_entity = Some(commands.spawn().insert(slider).id());
+                    to_send = Some("custom");
+                }
+
+                match ev.label.0 {
+                    "custom random seed" => {
+                        def.seed = rand::random();
+                        to_send = Some("custom");
+                    }
+                    "custom play" => {
+                        if def.check_generator().is_err() {
+                            continue;
+                        }
+                        settings.set(CUSTOM_SETTINGS_KEY, &*def).unwrap();
+                        *levelset = LevelSet {
+                            levels: vec![def.clone()],
+                            current_level: 0,
+                            title: "Custom Level".into(),
+                            settings_key: CUSTOM_KEY.into(),
+                            recipe: None,
+                        };
+                        spawn_level.send(SpawnLevelEvent { def: def.clone() });
+                    }
+                    _ => (),
+                }
+            }
+
+            // just our modal sender
+            if modal_sender.matches(&ev.sender) {
+                match ev.label.0 {
+                    "fewer holes" => def.num_holes = def.num_holes.saturating_sub(1).max(1),
+                    "more holes" => def.num_holes = (def.num_holes + 1).min(MAX_HOLES),
+                    "fewer blocks" => def.total_blocks = def.total_blocks.saturating_sub(1).max(1),
+                    "more blocks" => def.total_blocks = (def.total_blocks + 1).min(MAX_BLOCKS),
+                    "prev seed" => def.seed = def.seed.wrapping_sub(1),
+                    "next seed" => def.seed = def.seed.wrapping_add(1),
+                    "done" => {
+                        commands.entity(ev.sender).despawn_recursive();
+                        to_send = Some("custom");
+                        continue;
+                    }
+                    _ => continue,
+                }
+                modal_entity = Some(ev.sender);
+                to_send = Some("custom");
+            }
+        }
+
+        if let Some(action) = to_send {
+            evs.send(ActionEvent {
+                sender: sender.entity(),
+                label: ActionLabel(action),
+                target: None,
+            });
+            keep_position = true;
+        }
+    }
+}
+
 #[derive(Default, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EntityMarker {
     E(Entity),
@@ -1226,7 +1723,7 @@ pub enum EntityMarker {
 }
 
 impl EntityMarker {
-    fn entity(&self) -> Entity {
+    pub fn entity(&self) -> Entity {
         match self {
             EntityMarker::E(e) => *e,
             EntityMarker::None => {
@@ -1308,8 +1805,8 @@ pub fn spawn_controls(
 
                         for (ty, inputs) in inputs
                             .into_iter()
-                            .skip(if ev.label.0 == "controls 1" { 0 } else { 9 })
-                            .take(9)
+                            .skip(if ev.label.0 == "controls 1" { 0 } else { 10 })
+                            .take(10)
                         {
                             items.push((ty.as_str().into(), ActionLabel(""), false));
                             if Some(*ty) == *selection {
@@ -1333,9 +1830,6 @@ pub fn spawn_controls(
                             }
                         }
 
-                        items.push(("".into(), ActionLabel(""), false));
-                        items.push(("".into(), ActionLabel(""), false));
-
                         items.push(("".into(), ActionLabel(""), false));
                         if ev.label.0 == "controls 1" {
                             items.push(("Next".into(), ActionLabel("controls 2"), true));
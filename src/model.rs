@@ -1,19 +1,29 @@
 use bevy::prelude::*;
-use bevy::utils::HashSet;
+use bevy::utils::{HashMap, HashSet};
 use rand::{prelude::SliceRandom, Rng, RngCore};
+use serde::{Deserialize, Serialize};
 
-use crate::structs::{Position, PositionZ};
+use crate::bit_grid::BitGrid;
+
+#[derive(Component, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Position(pub IVec2);
+
+#[derive(Component, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PositionZ(pub i32);
 
 pub fn neighbours() -> [IVec2; 4] {
     [IVec2::X, IVec2::Y, -IVec2::X, -IVec2::Y]
 }
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Level {
     pub extents: IVec2,
     pub holes: Holes,
     pub planks: Vec<(Plank, Position)>,
     pub setup: bool,
+    // cuts the solver's solution takes, set when the level is built
+    #[serde(default)]
+    pub par: usize,
 }
 
 impl Level {
@@ -28,14 +38,88 @@ impl Level {
         });
 
         debug!("hole score {} * (1 + density {})", hole_difficulty, density);
-        return hole_difficulty * (1.0 + density);
+        hole_difficulty * (1.0 + density)
+    }
+
+    /// world position of the hole layout's origin
+    pub fn hole_origin(&self) -> IVec2 {
+        IVec2::new(-self.extents.x / 2, 1)
+    }
+
+    /// the remaining hole a plank at `pos` exactly fills, if any
+    pub fn matching_hole(&self, plank: &Plank, pos: Position) -> Option<usize> {
+        let mut shifted = plank.clone();
+        shifted.shift(pos.0 - self.hole_origin());
+        self.holes
+            .holes
+            .iter()
+            .position(|hole| shifted.equals(hole))
+    }
+
+    /// how a plank at `pos` misses the hole it covers most, in hole layout coords.
+    /// `None` if it covers no hole or fills one exactly
+    pub fn placement_diff(&self, plank: &Plank, pos: Position) -> Option<PlacementDiff> {
+        let mut shifted = plank.clone();
+        shifted.shift(pos.0 - self.hole_origin());
+        let (hole, covered) = self
+            .holes
+            .holes
+            .iter()
+            .map(|hole| shifted.coords.intersection(&hole.coords).count())
+            .enumerate()
+            .max_by_key(|(_, covered)| *covered)?;
+        if covered == 0 || shifted.equals(&self.holes.holes[hole]) {
+            return None;
+        }
+
+        let hole_coords = &self.holes.holes[hole].coords;
+        Some(PlacementDiff {
+            hole,
+            overhang: shifted.coords.difference(hole_coords).collect(),
+            missing: hole_coords.difference(&shifted.coords).collect(),
+        })
     }
+
+    /// a remaining hole the plank would fill once turned and moved into place, if any
+    pub fn hole_with_shape(&self, plank: &Plank) -> Option<usize> {
+        let key = plank.shape_key(false);
+        self.holes
+            .holes
+            .iter()
+            .position(|hole| hole.count() == plank.count() && hole.shape_key(false) == key)
+    }
+
+    /// the plank and hole shapes, sorted so the layout doesn't matter, for spotting the same puzzle in different sets
+    pub fn shape_keys(&self) -> (Vec<ShapeKey>, Vec<ShapeKey>) {
+        let sorted_keys = |sets: &mut dyn Iterator<Item = &CoordSet>| {
+            let mut keys = sets.map(|set| set.shape_key(false)).collect::<Vec<_>>();
+            keys.sort();
+            keys
+        };
+        (
+            sorted_keys(&mut self.planks.iter().map(|(plank, _)| plank)),
+            sorted_keys(&mut self.holes.holes.iter()),
+        )
+    }
+
+    pub fn is_won(&self) -> bool {
+        self.holes.holes.is_empty()
+    }
+}
+
+pub struct PlacementDiff {
+    pub hole: usize,
+    // plank cells outside the hole
+    pub overhang: Vec<IVec2>,
+    // hole cells the plank leaves empty
+    pub missing: Vec<IVec2>,
 }
 
 // holds the built level in initial state. probably not necessary with reproduceable seeded builds, could just use LevelDef
 #[derive(Default)]
 pub struct LevelBase(pub Level);
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UndoState {
     pub is_action: bool,
     pub level: Level,
@@ -44,6 +128,7 @@ pub struct UndoState {
     pub camera: (Position, PositionZ),
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct UndoBuffer {
     states: Vec<UndoState>,
     pos: usize,
@@ -119,6 +204,15 @@ impl UndoBuffer {
         Some(self.get_state(1))
     }
 
+    /// index of the current state, for checking undo from outside the game
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.states.len()
+    }
+
     pub fn has_back(&self) -> bool {
         self.pos > 0
     }
@@ -140,22 +234,19 @@ impl UndoBuffer {
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CoordSet {
-    pub coords: HashSet<IVec2>,
+    pub coords: BitGrid,
     pub turns: usize,
     pub texture_offset: IVec2,
 }
 
 impl CoordSet {
     pub fn extents(&self) -> ((i32, i32), (i32, i32)) {
-        let mut coords = self.coords.iter().copied();
-        let Some(first) = coords.next() else {
-            return ((0,0), (0,0))
+        let Some((min, max)) = self.coords.bounds() else {
+            return ((0, 0), (0, 0));
         };
-        coords.fold(((first.x, first.x), (first.y, first.y)), |(x, y), b| {
-            ((x.0.min(b.x), x.1.max(b.x)), (y.0.min(b.y), y.1.max(b.y)))
-        })
+        ((min.x, max.x), (min.y, max.y))
     }
 
     pub fn size(&self) -> IVec2 {
@@ -175,13 +266,11 @@ impl CoordSet {
     }
 
     pub fn touches(&self, other: &CoordSet) -> bool {
-        self.coords
-            .iter()
-            .any(|c1| neighbours().iter().any(|n| other.contains(*c1 + *n)))
+        self.coords.touches(&other.coords)
     }
 
     pub fn overlaps(&self, other: &CoordSet) -> bool {
-        self.coords.iter().any(|c1| other.contains(*c1))
+        self.coords.overlaps(&other.coords)
     }
 
     pub fn equals(&self, other: &CoordSet) -> bool {
@@ -189,23 +278,27 @@ impl CoordSet {
     }
 
     pub fn rotate(&mut self) {
-        self.coords = HashSet::from_iter(self.coords.drain().map(|c| IVec2::new(-c.y, c.x)));
+        self.coords = self.coords.rotated();
         self.turns = (self.turns + 1) % 4;
     }
 
+    /// rotate a quarter turn anticlockwise about the `pivot` cell, moving `pos` so the pivot stays put
+    pub fn rotate_about(&mut self, pos: &mut Position, pivot: IVec2) {
+        self.rotate();
+        let offset = pivot - pos.0;
+        let rotated = IVec2::new(-offset.y, offset.x);
+        pos.0 = pos.0 + offset - rotated;
+    }
+
     pub fn normalize(mut self) -> Self {
         let exts = self.extents();
-        self.coords = HashSet::from_iter(
-            self.coords
-                .drain()
-                .map(|c| c - IVec2::new(exts.0 .0, exts.1 .0)),
-        );
+        self.coords.shift(-IVec2::new(exts.0 .0, exts.1 .0));
         self.turns = 0;
         self
     }
 
     pub fn shift(&mut self, mut by: IVec2) {
-        self.coords = HashSet::from_iter(self.coords.drain().map(|c| c + by));
+        self.coords.shift(by);
 
         for _ in 0..self.turns {
             by = IVec2::new(by.y, -by.x);
@@ -214,18 +307,90 @@ impl CoordSet {
     }
 
     pub fn merge<'a>(holes: impl Iterator<Item = &'a CoordSet>) -> Hole {
-        let mut coords = HashSet::default();
-        for hole in holes {
-            coords.extend(hole.coords.iter());
-        }
-
         Hole {
-            coords,
+            coords: holes.flat_map(|hole| hole.coords.iter()).collect(),
             ..Default::default()
         }
     }
+
+    /// split into edge-connected pieces
+    pub fn connected_parts(&self) -> Vec<CoordSet> {
+        let mut remaining = self.coords.clone();
+        let mut parts = Vec::new();
+        while let Some(first) = remaining.iter().next() {
+            remaining.remove(&first);
+            let mut part = BitGrid::empty_like(&remaining);
+            part.insert(first);
+            let mut to_check = vec![first];
+            while let Some(cur) = to_check.pop() {
+                for n in neighbours() {
+                    let n = cur + n;
+                    if remaining.remove(&n) {
+                        part.insert(n);
+                        to_check.push(n);
+                    }
+                }
+            }
+            parts.push(CoordSet {
+                coords: part,
+                ..Default::default()
+            });
+        }
+        parts
+    }
+
+    /// a key that's the same wherever the shape is placed and however it's turned, and mirrored too if
+    /// `reflect`: the smallest sorted cell list of the normalized orientations
+    pub fn shape_key(&self, reflect: bool) -> ShapeKey {
+        let flips: &[bool] = if reflect { &[false, true] } else { &[false] };
+        flips
+            .iter()
+            .flat_map(|&flip| {
+                (0..4).map(move |turns| {
+                    let transform = |c: IVec2| {
+                        let mut c = if flip { IVec2::new(-c.x, c.y) } else { c };
+                        for _ in 0..turns {
+                            c = IVec2::new(-c.y, c.x);
+                        }
+                        c
+                    };
+                    let cells = self.coords.iter().map(transform);
+                    let min = cells.clone().fold(IVec2::splat(i32::MAX), IVec2::min);
+                    let mut cells = cells
+                        .map(|c| (c.x - min.x, c.y - min.y))
+                        .collect::<Vec<_>>();
+                    cells.sort_unstable();
+                    ShapeKey(cells)
+                })
+            })
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// same cells after moving and turning, and mirroring if `reflect`
+    pub fn same_shape(&self, other: &CoordSet, reflect: bool) -> bool {
+        self.count() == other.count() && self.shape_key(reflect) == other.shape_key(reflect)
+    }
+
+    /// quarter turns anticlockwise to make this the shape of `hole`, if it can be
+    pub fn turns_to_fit(&self, hole: &CoordSet) -> Option<usize> {
+        if self.count() != hole.count() {
+            return None;
+        }
+        let hole = hole.clone().normalize();
+        let mut turned = self.clone();
+        (0..4).find(|_| {
+            let fits = turned.clone().normalize().equals(&hole);
+            turned.rotate();
+            fits
+        })
+    }
 }
 
+/// see `CoordSet::shape_key`
+#[derive(Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ShapeKey(Vec<(i32, i32)>);
+
 impl std::fmt::Display for CoordSet {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let exts = self.extents();
@@ -235,7 +400,8 @@ impl std::fmt::Display for CoordSet {
             f.write_str("-")?;
         }
         f.write_str("|\n")?;
-        for row in exts.1 .0..=exts.1 .1 {
+        // top row first, like the grids in level files
+        for row in (exts.1 .0..=exts.1 .1).rev() {
             f.write_str("|")?;
             for col in exts.0 .0..=exts.0 .1 {
                 if self.contains_xy(col, row) {
@@ -259,12 +425,39 @@ impl std::fmt::Display for CoordSet {
 pub type Hole = CoordSet;
 pub type Plank = CoordSet;
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Serialize, Deserialize)]
 pub struct Holes {
     pub holes: Vec<Hole>,
 }
 
+impl Holes {
+    /// how many holes are the same shape as an earlier one
+    pub fn duplicate_shapes(&self) -> usize {
+        let keys = self
+            .holes
+            .iter()
+            .map(|hole| hole.shape_key(false))
+            .collect::<HashSet<_>>();
+        self.holes.len() - keys.len()
+    }
+}
+
 impl Plank {
+    /// a plank of the cells of an rgba image that aren't white, the top row highest, normalized
+    pub fn from_pixels(data: &[u8], width: usize) -> Self {
+        let coords = data
+            .chunks(4)
+            .enumerate()
+            .filter(|(_, word)| word.iter().any(|b| *b < 254))
+            .map(|(i, _)| IVec2::new((i % width) as i32, -((i / width) as i32)))
+            .collect();
+        Plank {
+            coords,
+            ..Default::default()
+        }
+        .normalize()
+    }
+
     pub fn from_holes(holes: &Holes, mut rng: &mut (impl RngCore + ?Sized)) -> Self {
         let mut indexes = (0..holes.holes.len()).collect::<Vec<_>>();
         indexes.shuffle(&mut rng);
@@ -274,8 +467,8 @@ impl Plank {
             ..Default::default()
         };
 
-        for i in 1..indexes.len() {
-            plank = plank.attach_hole(&holes.holes[indexes[i]], &mut rng);
+        for &i in indexes.iter().skip(1) {
+            plank = plank.attach_hole(&holes.holes[i], &mut rng);
         }
 
         for _ in 0..rng.gen_range::<u64, _>(0..4) {
@@ -320,16 +513,16 @@ impl Plank {
         }
 
         // possible.shuffle(rng);
-        let mut hole = possible.pop().unwrap();
+        let hole = possible.pop().unwrap();
 
-        self.coords.extend(hole.coords.drain());
+        self.coords.extend(hole.coords.iter());
         self
     }
 }
 
 pub fn gen_hole(size: usize, rng: &mut impl RngCore) -> Hole {
     let mut hole = Hole {
-        coords: HashSet::from_iter(std::iter::once(IVec2::ZERO)),
+        coords: std::iter::once(IVec2::ZERO).collect(),
         turns: 0,
         texture_offset: IVec2::new(
             rng.gen_range::<i32, _>(0..100),
@@ -386,5 +579,605 @@ pub fn gen_holes(mut count: usize, total: usize, mut rng: &mut (impl RngCore + ?
         remainder -= size as usize;
     }
 
-    Holes { holes }
+    let holes = Holes { holes };
+    debug!("{} duplicate hole shapes", holes.duplicate_shapes());
+    holes
+}
+
+// solver
+
+/// a piece of a solved level, in the coordinates of the plank it is cut from
+#[derive(Clone, Debug)]
+pub struct SolvedPiece {
+    pub plank: usize,
+    pub hole: usize,
+    // number of `rotate` calls needed to line the piece up with its hole
+    pub turns: usize,
+    pub coords: CoordSet,
+}
+
+/// a single cut, as the (min, max) cell pairs a `Cut` would separate, in the coordinates of the original plank
+#[derive(Clone, Debug)]
+pub struct SolvedCut {
+    pub plank: usize,
+    pub separated: HashSet<(IVec2, IVec2)>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Solution {
+    pub pieces: Vec<SolvedPiece>,
+    // in the order they can be made
+    pub cuts: Vec<SolvedCut>,
+}
+
+impl Solution {
+    pub fn cut_count(&self) -> usize {
+        self.cuts.len()
+    }
+}
+
+struct Orientation {
+    // relative to the first cell in scan order
+    cells: Vec<IVec2>,
+    turns: usize,
+}
+
+struct Shape {
+    // hole index, and the turns from the first hole's shape to this one
+    holes: Vec<(usize, usize)>,
+    available: usize,
+    size: usize,
+    orientations: Vec<Orientation>,
+}
+
+struct PlankCells {
+    cells: Vec<IVec2>,
+    index: HashMap<IVec2, usize>,
+    covered: Vec<bool>,
+}
+
+struct Search {
+    planks: Vec<PlankCells>,
+    shapes: Vec<Shape>,
+    // (plank, shape, orientation, anchor)
+    placed: Vec<(usize, usize, usize, IVec2)>,
+    steps_left: usize,
+}
+
+fn scan_order(a: &IVec2, b: &IVec2) -> std::cmp::Ordering {
+    (a.y, a.x).cmp(&(b.y, b.x))
+}
+
+fn orientations(hole: &Hole) -> Vec<Orientation> {
+    let mut result: Vec<Orientation> = Vec::new();
+    let mut rotated = hole.clone();
+    for turns in 0..4 {
+        let mut cells = rotated.coords.iter().collect::<Vec<_>>();
+        cells.sort_by(scan_order);
+        let anchor = cells[0];
+        let cells = cells.into_iter().map(|c| c - anchor).collect::<Vec<_>>();
+        if !result.iter().any(|o| o.cells == cells) {
+            result.push(Orientation { cells, turns });
+        }
+        rotated.rotate();
+    }
+    result
+}
+
+impl Search {
+    fn fits(&self, plank: usize, cells: &[IVec2], anchor: IVec2) -> bool {
+        let plank = &self.planks[plank];
+        cells.iter().all(|c| match plank.index.get(&(anchor + *c)) {
+            Some(&i) => !plank.covered[i],
+            None => false,
+        })
+    }
+
+    fn set_covered(&mut self, plank: usize, cells: &[IVec2], anchor: IVec2, covered: bool) {
+        let plank = &mut self.planks[plank];
+        for c in cells.iter() {
+            let i = plank.index[&(anchor + *c)];
+            plank.covered[i] = covered;
+        }
+    }
+
+    // every uncovered region must be able to take at least the smallest remaining hole
+    fn regions_ok(&self, plank: usize) -> bool {
+        let Some(smallest) = self
+            .shapes
+            .iter()
+            .filter(|s| s.available > 0)
+            .map(|s| s.size)
+            .min()
+        else {
+            return true;
+        };
+
+        let plank = &self.planks[plank];
+        let mut seen = plank.covered.clone();
+        for start in 0..plank.cells.len() {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut size = 0;
+            let mut to_check = vec![plank.cells[start]];
+            while let Some(cur) = to_check.pop() {
+                size += 1;
+                for n in neighbours() {
+                    if let Some(&i) = plank.index.get(&(cur + n)) {
+                        if !seen[i] {
+                            seen[i] = true;
+                            to_check.push(cur + n);
+                        }
+                    }
+                }
+            }
+            if size < smallest {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn search(&mut self, mut plank: usize, mut start: usize) -> bool {
+        if self.steps_left == 0 {
+            return false;
+        }
+        self.steps_left -= 1;
+
+        // find the first uncovered cell
+        let target = loop {
+            let Some(cells) = self.planks.get(plank) else {
+                return true;
+            };
+            match (start..cells.cells.len()).find(|&i| !cells.covered[i]) {
+                Some(i) => break i,
+                None => {
+                    plank += 1;
+                    start = 0;
+                }
+            }
+        };
+        let target_coord = self.planks[plank].cells[target];
+
+        for shape in 0..self.shapes.len() {
+            if self.shapes[shape].available == 0 {
+                continue;
+            }
+
+            for orientation in 0..self.shapes[shape].orientations.len() {
+                let cells = self.shapes[shape].orientations[orientation].cells.clone();
+                if self.fits(plank, &cells, target_coord) {
+                    self.set_covered(plank, &cells, target_coord, true);
+                    self.shapes[shape].available -= 1;
+                    self.placed.push((plank, shape, orientation, target_coord));
+
+                    if self.regions_ok(plank) && self.search(plank, target + 1) {
+                        return true;
+                    }
+
+                    self.placed.pop();
+                    self.shapes[shape].available += 1;
+                    self.set_covered(plank, &cells, target_coord, false);
+                }
+            }
+        }
+
+        false
+    }
+}
+
+pub fn is_connected(coords: &BitGrid) -> bool {
+    let Some(first) = coords.iter().next() else {
+        return true;
+    };
+
+    let mut connected = BitGrid::empty_like(coords);
+    connected.insert(first);
+    let mut to_check = vec![first];
+    while let Some(cur) = to_check.pop() {
+        for n in neighbours() {
+            let n = cur + n;
+            if coords.contains(&n) && connected.insert(n) {
+                to_check.push(n);
+            }
+        }
+    }
+
+    connected.len() == coords.len()
+}
+
+// order the cuts so each one splits a single piece from a connected remainder
+fn plan_cuts(plank: usize, pieces: &[&SolvedPiece]) -> Vec<SolvedCut> {
+    let mut remaining = BitGrid::new();
+    for piece in pieces.iter() {
+        remaining.extend(piece.coords.coords.iter());
+    }
+    let mut pieces = pieces.to_vec();
+
+    let mut cuts = Vec::new();
+    while pieces.len() > 1 {
+        let (i, rest) = pieces
+            .iter()
+            .enumerate()
+            .find_map(|(i, piece)| {
+                let rest = remaining
+                    .difference(&piece.coords.coords)
+                    .collect::<BitGrid>();
+                is_connected(&rest).then_some((i, rest))
+            })
+            .expect("a tree of pieces always has a leaf");
+
+        let mut separated = HashSet::default();
+        for c in pieces[i].coords.coords.iter() {
+            for n in neighbours() {
+                let n = c + n;
+                if rest.contains(&n) {
+                    separated.insert((n.min(c), n.max(c)));
+                }
+            }
+        }
+
+        cuts.push(SolvedCut { plank, separated });
+        pieces.remove(i);
+        remaining = rest;
+    }
+
+    cuts
+}
+
+/// the search ran out of steps before finding a tiling or ruling one out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GaveUp;
+
+/// find the cuts that split the level's planks into pieces matching each hole up to rotation.
+/// a cut splits one piece into two, so a solvable level always takes holes - planks cuts; the work is
+/// in finding a tiling of the planks by the hole shapes. returns None if there is no such tiling.
+/// the search is exponential, big levels can take seconds, see `solve_within`
+pub fn solve(level: &Level) -> Option<Solution> {
+    solve_within(level, usize::MAX).unwrap_or_default()
+}
+
+/// `solve`, giving up after trying `steps` placements
+pub fn solve_within(level: &Level, steps: usize) -> Result<Option<Solution>, GaveUp> {
+    let holes = &level.holes.holes;
+
+    let plank_area: usize = level.planks.iter().map(|(plank, _)| plank.count()).sum();
+    let hole_area: usize = holes.iter().map(|hole| hole.count()).sum();
+    if plank_area != hole_area {
+        debug!("solve: area mismatch {} vs {}", plank_area, hole_area);
+        return Ok(None);
+    }
+
+    // group holes of the same shape so equivalent assignments are only tried once
+    let mut shapes: Vec<Shape> = Vec::new();
+    for (i, hole) in holes.iter().enumerate() {
+        let hole_orientations = orientations(hole);
+        let existing = shapes.iter_mut().find_map(|shape| {
+            let turns = shape
+                .orientations
+                .iter()
+                .find(|o| o.cells == hole_orientations[0].cells)?
+                .turns;
+            Some((shape, turns))
+        });
+        match existing {
+            Some((shape, turns)) => {
+                shape.holes.push((i, turns));
+                shape.available += 1;
+            }
+            None => shapes.push(Shape {
+                holes: vec![(i, 0)],
+                available: 1,
+                size: hole.count(),
+                orientations: hole_orientations,
+            }),
+        }
+    }
+    // big pieces first, they are the most constrained
+    shapes.sort_by_key(|shape| std::cmp::Reverse(shape.size));
+
+    let planks = level
+        .planks
+        .iter()
+        .map(|(plank, _)| {
+            let mut cells = plank.coords.iter().collect::<Vec<_>>();
+            cells.sort_by(scan_order);
+            let index = cells.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+            PlankCells {
+                covered: vec![false; cells.len()],
+                cells,
+                index,
+            }
+        })
+        .collect();
+
+    let mut search = Search {
+        planks,
+        shapes,
+        placed: Vec::new(),
+        steps_left: steps,
+    };
+
+    if !search.search(0, 0) {
+        if search.steps_left == 0 {
+            debug!("solve: gave up after {} steps", steps);
+            return Err(GaveUp);
+        }
+        debug!("solve: no tiling");
+        return Ok(None);
+    }
+
+    let mut next_hole = vec![0; search.shapes.len()];
+    let pieces = search
+        .placed
+        .iter()
+        .map(|&(plank, shape, orientation, anchor)| {
+            let shape_data = &search.shapes[shape];
+            let orientation = &shape_data.orientations[orientation];
+            let (hole, hole_turns) = shape_data.holes[next_hole[shape]];
+            next_hole[shape] += 1;
+
+            let source = &level.planks[plank].0;
+            SolvedPiece {
+                plank,
+                hole,
+                turns: (4 + hole_turns - orientation.turns) % 4,
+                coords: CoordSet {
+                    coords: orientation.cells.iter().map(|c| anchor + *c).collect(),
+                    turns: source.turns,
+                    texture_offset: source.texture_offset,
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let cuts = (0..level.planks.len())
+        .flat_map(|plank| {
+            let plank_pieces = pieces
+                .iter()
+                .filter(|piece| piece.plank == plank)
+                .collect::<Vec<_>>();
+            plan_cuts(plank, &plank_pieces)
+        })
+        .collect();
+
+    Ok(Some(Solution { pieces, cuts }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // '#' cells, the first row highest
+    fn shape(rows: &[&str]) -> CoordSet {
+        let coords = rows
+            .iter()
+            .rev()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '#')
+                    .map(move |(x, _)| IVec2::new(x as i32, y as i32))
+            })
+            .collect();
+        CoordSet {
+            coords,
+            ..Default::default()
+        }
+    }
+
+    fn level(plank: &[&str], holes: &[&[&str]]) -> Level {
+        Level {
+            holes: Holes {
+                holes: holes.iter().map(|rows| shape(rows)).collect(),
+            },
+            planks: vec![(shape(plank), Position::default())],
+            ..Default::default()
+        }
+    }
+
+    // the pieces of a solution cover the plank once each and match their holes
+    fn check_pieces(level: &Level, solution: &Solution) {
+        let mut covered = BitGrid::new();
+        for piece in solution.pieces.iter() {
+            assert!(!covered.overlaps(&piece.coords.coords));
+            covered.extend(piece.coords.coords.iter());
+
+            let mut turned = piece.coords.clone();
+            for _ in 0..piece.turns {
+                turned.rotate();
+            }
+            let hole = &level.holes.holes[piece.hole];
+            assert_eq!(turned.normalize().coords, hole.clone().normalize().coords);
+        }
+        assert_eq!(covered, level.planks[0].0.coords);
+    }
+
+    #[test]
+    fn solves_tiny_levels() {
+        let check = |plank: &[&str], holes: &[&[&str]], cuts: usize| {
+            let level = level(plank, holes);
+            let solution = solve(&level).expect("solvable");
+            assert_eq!(solution.cut_count(), cuts);
+            assert_eq!(solution.pieces.len(), holes.len());
+            check_pieces(&level, &solution);
+        };
+
+        check(&["#"], &[&["#"]], 0);
+        check(&["##"], &[&["#"], &["#"]], 1);
+        check(&["##", "##"], &[&["#", "#"], &["##"]], 1);
+        check(&["###", "#.."], &[&["#", "#"], &["#"], &["#"]], 2);
+    }
+
+    #[test]
+    fn solves_turned_pieces() {
+        // the L only fits the plank turned
+        let level = level(&["###", "..#"], &[&["#.", "##"], &["#"]]);
+        let solution = solve(&level).expect("solvable");
+        assert_eq!(solution.cut_count(), 1);
+        check_pieces(&level, &solution);
+    }
+
+    #[test]
+    fn unsolvable_levels() {
+        // a T can't be cut from a straight plank
+        assert!(solve(&level(&["####"], &[&["###", ".#."]])).is_none());
+        // an S can't be cut from an L of the same size, turned or not
+        assert!(solve(&level(&["#.", "#.", "##"], &[&[".##", "##."]])).is_none());
+        // a square doesn't fit either of two straight planks, the singles only pad the area
+        let mut straights = level(&["###"], &[&["##", "##"], &["#"], &["#"]]);
+        straights
+            .planks
+            .push((shape(&["###"]), Position::default()));
+        assert!(solve(&straights).is_none());
+        // the straight three only fits across the middle of the cross, leaving single arms that
+        // can't make the two
+        assert!(solve(&level(&[".#.", "###", ".#."], &[&["###"], &["##"]])).is_none());
+        // the areas differ
+        assert!(solve(&level(&["##", "#."], &[&["##"], &["#"], &["#"]])).is_none());
+        assert!(solve(&level(&["###"], &[&["##"]])).is_none());
+    }
+
+    #[test]
+    fn shape_keys_ignore_placement() {
+        let l = shape(&["#..", "###"]);
+        let key = l.shape_key(false);
+        let mut turned = l.clone();
+        for _ in 0..4 {
+            turned.rotate();
+            for offset in [IVec2::ZERO, IVec2::new(5, -3), IVec2::new(-70, 64)] {
+                let mut moved = turned.clone();
+                moved.shift(offset);
+                assert_eq!(moved.shape_key(false), key);
+            }
+        }
+
+        // a mirror image only matches when reflecting
+        let mirrored = shape(&["..#", "###"]);
+        assert_ne!(mirrored.shape_key(false), key);
+        assert_eq!(mirrored.shape_key(true), l.shape_key(true));
+        assert_ne!(
+            shape(&["###."]).shape_key(true),
+            shape(&["##", "##"]).shape_key(true)
+        );
+    }
+
+    #[test]
+    fn turns_to_fit() {
+        let l = shape(&["#..", "###"]);
+        assert_eq!(l.turns_to_fit(&shape(&["#..", "###"])), Some(0));
+        assert_eq!(l.turns_to_fit(&shape(&[".#", ".#", "##"])), Some(1));
+        assert_eq!(l.turns_to_fit(&shape(&["##", "#.", "#."])), Some(3));
+
+        // placed anywhere
+        let mut moved = shape(&["###", "..#"]);
+        moved.shift(IVec2::new(7, -4));
+        assert_eq!(l.turns_to_fit(&moved), Some(2));
+
+        // the mirror image, the same area in another shape, and a different area
+        assert_eq!(l.turns_to_fit(&shape(&["..#", "###"])), None);
+        assert_eq!(l.turns_to_fit(&shape(&["####"])), None);
+        assert_eq!(l.turns_to_fit(&shape(&["#...", "####"])), None);
+    }
+
+    #[test]
+    fn placement_diff() {
+        // a square and a single cell to its right at (3, 0)
+        let mut level = level(&["#"], &[&["##", "##"], &["#"]]);
+        level.holes.holes[1].shift(IVec2::new(3, 0));
+        let origin = level.hole_origin();
+        let at = |offset: IVec2| Position(origin + offset);
+
+        // hangs over the right of the square and leaves its top row empty
+        let diff = level
+            .placement_diff(&shape(&["###"]), at(IVec2::ZERO))
+            .expect("misses");
+        assert_eq!(diff.hole, 0);
+        assert_eq!(diff.overhang, vec![IVec2::new(2, 0)]);
+        let mut missing = diff.missing.clone();
+        missing.sort_by_key(|c| (c.x, c.y));
+        assert_eq!(missing, vec![IVec2::new(0, 1), IVec2::new(1, 1)]);
+
+        // covers the square more than the single cell
+        let diff = level
+            .placement_diff(&shape(&["####"]), at(IVec2::ZERO))
+            .expect("misses");
+        assert_eq!(diff.hole, 0);
+        assert_eq!(diff.overhang.len(), 2);
+
+        // an exact fill, or covering nothing, isn't a miss
+        assert!(level
+            .placement_diff(&shape(&["##", "##"]), at(IVec2::ZERO))
+            .is_none());
+        assert!(level
+            .placement_diff(&shape(&["#"]), at(IVec2::new(3, 0)))
+            .is_none());
+        assert!(level
+            .placement_diff(&shape(&["##"]), at(IVec2::new(0, 5)))
+            .is_none());
+    }
+
+    #[test]
+    fn step_budget() {
+        let tiny = level(&["###", "#.."], &[&["#", "#"], &["#"], &["#"]]);
+        assert!(matches!(solve_within(&tiny, 1), Err(GaveUp)));
+        let solution = solve_within(&tiny, 1000).unwrap().expect("solvable");
+        assert_eq!(solution.cut_count(), 2);
+
+        // ruled out within budget
+        assert_eq!(
+            solve_within(&level(&["####"], &[&["###", ".#."]]), 1000).map(|s| s.is_some()),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn cuts_leave_connected_remainder() {
+        // a row of pieces listed middle first: cutting the middle first would leave two parts
+        let pieces = ["..#..", "#....", ".##..", "...##"]
+            .iter()
+            .enumerate()
+            .map(|(hole, row)| SolvedPiece {
+                plank: 0,
+                hole,
+                turns: 0,
+                coords: shape(&[row]),
+            })
+            .collect::<Vec<_>>();
+        let cuts = plan_cuts(0, &pieces.iter().collect::<Vec<_>>());
+        assert_eq!(cuts.len(), pieces.len() - 1);
+
+        // the edges between a piece and the rest of the plank
+        let edges = |piece: &SolvedPiece, rest: &BitGrid| {
+            let mut edges = HashSet::default();
+            for c in piece.coords.coords.iter() {
+                for n in neighbours() {
+                    if rest.contains(&(c + n)) {
+                        edges.insert(((c + n).min(c), (c + n).max(c)));
+                    }
+                }
+            }
+            edges
+        };
+
+        let mut remaining = shape(&["#####"]).coords;
+        let mut left = pieces.iter().collect::<Vec<_>>();
+        for cut in cuts.iter() {
+            // each cut separates one piece from everything else left
+            let i = left
+                .iter()
+                .position(|piece| {
+                    let rest = remaining.difference(&piece.coords.coords).collect();
+                    edges(piece, &rest) == cut.separated
+                })
+                .expect("cut separates a piece");
+            let piece = left.remove(i);
+            remaining = remaining.difference(&piece.coords.coords).collect();
+            assert!(is_connected(&remaining));
+        }
+        assert_eq!(left.len(), 1);
+    }
 }
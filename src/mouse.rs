@@ -0,0 +1,324 @@
+// play with the mouse. the pointer walks the cursor (or the cutter) to the cell under it a step at a time,
+// and clicks, drags and the wheel press the same action types a key bound to them would. buttons and wheel
+// directions bound in the controls do what they're bound to instead
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{touch::Touches, InputSystem},
+    prelude::*,
+    render::camera::{Camera, Camera3d},
+};
+use bevy_carp::cut::{Cut, CutStep};
+
+use crate::{
+    input::{read_mouse, ActionInputs, ActionType, Controller, InputItem, MouseInputs},
+    replay::Replaying,
+    structs::{ActionEvent, Position},
+    Cursor, PlankComponent, Selected, Targeted, PLANK_Z,
+};
+
+// how long after a finger lifts to keep ignoring the mouse, for the clicks a touchscreen makes up
+const TOUCH_GRACE: f64 = 0.5;
+
+pub struct MousePlugin;
+
+impl Plugin for MousePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Touching>()
+            .add_system(show_pointer)
+            .add_system_to_stage(CoreStage::PreUpdate, note_touches.after(InputSystem))
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                mouse_play.after(read_mouse).after(note_touches),
+            );
+    }
+}
+
+// the system pointer is hidden for keys and pads, and shown again when the mouse moves
+fn show_pointer(
+    mut windows: ResMut<Windows>,
+    mut moved: EventReader<CursorMoved>,
+    keys: Res<Input<KeyCode>>,
+    mut pad: EventReader<GamepadEvent>,
+) {
+    let Some(window) = windows.get_primary_mut() else {
+        return;
+    };
+
+    let pad_used = pad.iter().any(|GamepadEvent(_, ev)| match ev {
+        GamepadEventType::ButtonChanged(_, value) => *value > 0.5,
+        GamepadEventType::AxisChanged(_, value) => value.abs() > 0.5,
+        _ => false,
+    });
+    if moved.iter().last().is_some() {
+        window.set_cursor_visibility(true);
+    } else if pad_used || keys.get_just_pressed().next().is_some() {
+        window.set_cursor_visibility(false);
+    }
+}
+
+/// the level as a pointer sees it
+#[derive(SystemParam)]
+pub struct PointerPlay<'w, 's> {
+    windows: Res<'w, Windows>,
+    camera: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<Camera3d>>,
+    controllers: Query<'w, 's, (Entity, &'static Controller, Option<&'static Replaying>)>,
+    cursor: Query<'w, 's, &'static Position, With<Cursor>>,
+    cutter: Query<'w, 's, (&'static Position, &'static Cut)>,
+    targeted: Query<'w, 's, (&'static PlankComponent, &'static Position), With<Targeted>>,
+    planks: Query<'w, 's, (&'static PlankComponent, &'static Position)>,
+    selected: Query<'w, 's, (), With<Selected>>,
+    actions: EventWriter<'w, 's, ActionEvent>,
+}
+
+impl PointerPlay<'_, '_> {
+    /// where a point in the window meets the plane the planks lie in
+    pub fn board(&self, pointer: Vec2) -> Option<Vec2> {
+        let window = self.windows.get_primary()?;
+        let (camera, transform) = self.camera.get_single().ok()?;
+        let ndc = pointer / Vec2::new(window.width(), window.height()) * 2.0 - Vec2::ONE;
+        let ndc_to_world = transform.compute_matrix() * camera.projection_matrix.inverse();
+        // reversed depth, 1 is the near plane
+        let near = ndc_to_world.project_point3(ndc.extend(1.0));
+        let far = ndc_to_world.project_point3(ndc.extend(0.5));
+        let dir = far - near;
+        if dir.z.abs() < f32::EPSILON {
+            return None;
+        }
+        let t = (PLANK_Z - near.z) / dir.z;
+        (t > 0.0).then(|| (near + dir * t).truncate())
+    }
+
+    /// the board under the mouse
+    pub fn mouse(&self) -> Option<Vec2> {
+        self.windows
+            .get_primary()?
+            .cursor_position()
+            .and_then(|pointer| self.board(pointer))
+    }
+
+    /// press `ty` the way a key bound to it would, for every controller listening for it
+    pub fn press(&mut self, ty: ActionType) {
+        for (ent, controller, replaying) in self.controllers.iter() {
+            if !controller.enabled || replaying.is_some() {
+                continue;
+            }
+            for (_, action) in controller.actions.iter().filter(|(t, _)| *t == ty) {
+                self.actions.send(ActionEvent {
+                    sender: ent,
+                    label: action.label,
+                    target: None,
+                });
+            }
+        }
+    }
+
+    pub fn plank_at(&self, cell: IVec2) -> bool {
+        self.planks
+            .iter()
+            .any(|(plank, pos)| plank.0.contains(cell - pos.0))
+    }
+
+    pub fn holding(&self) -> bool {
+        !self.selected.is_empty()
+    }
+
+    fn cutting(&self) -> Option<(IVec2, &Cut)> {
+        self.cutter.get_single().ok().map(|(pos, cut)| (pos.0, cut))
+    }
+
+    // take a step towards `point`. true once there or when no step gets closer, none when there's nothing
+    // to move
+    fn step_towards(&mut self, point: Vec2) -> Option<bool> {
+        // the cutter runs along the lines between cells, the cursor sits in them
+        let cutting = self.cutting();
+        let (pos, target) = match cutting {
+            Some((pos, _)) => (pos, point.round().as_ivec2()),
+            None => (self.cursor.get_single().ok()?.0, point.floor().as_ivec2()),
+        };
+        let delta = target - pos;
+
+        // the longer way first, or the other way if a cut can't go that way
+        let x_step = (delta.x != 0).then(|| IVec2::new(delta.x.signum(), 0));
+        let y_step = (delta.y != 0).then(|| IVec2::new(0, delta.y.signum()));
+        let steps = match delta.x.abs() >= delta.y.abs() {
+            true => [x_step, y_step],
+            false => [y_step, x_step],
+        };
+        let step = steps.into_iter().flatten().find(|step| {
+            let (Some((_, cut)), Ok((plank, plank_pos))) = (cutting, self.targeted.get_single())
+            else {
+                return true;
+            };
+            !matches!(
+                cut.clone().extend(&plank.0, plank_pos.0, pos, pos + *step),
+                CutStep::Blocked
+            )
+        });
+
+        match step.map(|step| (step.x, step.y)) {
+            Some((-1, 0)) => self.press(ActionType::MoveLeft),
+            Some((1, 0)) => self.press(ActionType::MoveRight),
+            Some((0, -1)) => self.press(ActionType::MoveDown),
+            Some((0, 1)) => self.press(ActionType::MoveUp),
+            _ => return Some(true),
+        }
+        Some(false)
+    }
+}
+
+enum Pending {
+    Press(ActionType),
+    // finish the cut if it's through, otherwise drop it
+    EndCut,
+}
+
+/// a press, drag and release of the mouse button or a finger. a press and release in the same cell grabs or
+/// drops, dragging off a plank cuts it, and anything else just moves the cursor
+#[derive(Default)]
+pub struct Gesture {
+    // where the pointer is on the board, until the cursor gets there
+    point: Option<Vec2>,
+    // the cell the press started in, until it's a click or a drag
+    pressed_on: Option<IVec2>,
+    // the current cut was started by dragging, so ends with the release
+    dragging: bool,
+    // done when the cursor catches up
+    pending: Option<Pending>,
+}
+
+impl Gesture {
+    pub fn move_to(&mut self, point: Vec2) {
+        self.point = Some(point);
+    }
+
+    pub fn down(&mut self, play: &PointerPlay, point: Vec2) {
+        self.point = Some(point);
+        if play.cutting().is_none() {
+            self.pressed_on = Some(point.floor().as_ivec2());
+        }
+    }
+
+    pub fn up(&mut self) {
+        if self.pressed_on.take().is_some() {
+            self.pending = Some(Pending::Press(ActionType::MainAction));
+        }
+        if std::mem::take(&mut self.dragging) {
+            self.pending = Some(Pending::EndCut);
+        }
+    }
+
+    /// forget the press, dropping any cut it started
+    pub fn cancel(&mut self, play: &mut PointerPlay) {
+        self.pressed_on = None;
+        self.pending = None;
+        if std::mem::take(&mut self.dragging) && play.cutting().is_some() {
+            play.press(ActionType::SecondAction);
+        }
+    }
+
+    pub fn update(&mut self, play: &mut PointerPlay, held: bool) {
+        let Some(point) = self.point else {
+            return;
+        };
+
+        // once the cursor is where the press started, moving away cuts the plank there, or just moves
+        if let Some(from) = self.pressed_on.filter(|_| held) {
+            let cursor_there = play.cursor.get_single().is_ok_and(|pos| pos.0 == from);
+            if point.floor().as_ivec2() != from && cursor_there {
+                self.pressed_on = None;
+                if !play.holding() && !play.targeted.is_empty() {
+                    play.press(ActionType::SecondAction);
+                    self.dragging = true;
+                }
+            }
+        }
+
+        let target = match self.pressed_on {
+            Some(from) => from.as_vec2() + 0.5,
+            None => point,
+        };
+        match play.step_towards(target) {
+            None => *self = Self::default(),
+            Some(false) => (),
+            Some(true) => {
+                match self.pending.take() {
+                    Some(Pending::Press(ty)) => play.press(ty),
+                    Some(Pending::EndCut) => match play.cutting() {
+                        Some((_, cut)) if cut.finished => play.press(ActionType::MainAction),
+                        Some(_) => play.press(ActionType::SecondAction),
+                        None => (),
+                    },
+                    None => (),
+                }
+                // leave the keys and pad to it
+                if !held && self.pressed_on.is_none() && !self.dragging {
+                    self.point = None;
+                }
+            }
+        }
+    }
+}
+
+// touchscreens move the system pointer and click as well, so the mouse is left alone while a finger is down
+// and for a moment after, and the touch systems have the fingers
+#[derive(Default)]
+struct Touching(bool);
+
+fn note_touches(
+    touches: Res<Touches>,
+    time: Res<Time>,
+    mut touching: ResMut<Touching>,
+    mut last: Local<Option<f64>>,
+) {
+    let now = time.seconds_since_startup();
+    if touches.iter().next().is_some() || touches.iter_just_released().next().is_some() {
+        *last = Some(now);
+    }
+    touching.0 = last.is_some_and(|at| now - at < TOUCH_GRACE);
+}
+
+fn mouse_play(
+    buttons: Res<Input<MouseButton>>,
+    mouse: Res<MouseInputs>,
+    inputs: Res<ActionInputs>,
+    touching: Res<Touching>,
+    mut moved: EventReader<CursorMoved>,
+    mut play: PointerPlay,
+    mut gesture: Local<Gesture>,
+) {
+    let moved = moved.iter().last().is_some();
+
+    if touching.0 {
+        *gesture = Gesture::default();
+        return;
+    }
+    let free = |item| !inputs.is_bound(&item);
+    let left = free(InputItem::Mouse(MouseButton::Left));
+
+    // egui has the pointer over menus and panels
+    if !mouse.over_ui {
+        if let Some(point) = play.mouse() {
+            if left && buttons.just_pressed(MouseButton::Left) {
+                gesture.down(&play, point);
+            } else if moved {
+                gesture.move_to(point);
+            }
+        }
+
+        if mouse.scroll > 0.0 && free(InputItem::Wheel(true)) {
+            play.press(ActionType::ZoomIn);
+        } else if mouse.scroll < 0.0 && free(InputItem::Wheel(false)) {
+            play.press(ActionType::ZoomOut);
+        }
+
+        if buttons.just_pressed(MouseButton::Right) && free(InputItem::Mouse(MouseButton::Right)) {
+            play.press(ActionType::TurnLeft);
+        }
+    }
+
+    if left && buttons.just_released(MouseButton::Left) {
+        gesture.up();
+    }
+    gesture.update(&mut play, left && buttons.pressed(MouseButton::Left));
+}
@@ -0,0 +1,56 @@
+// curated level packs shipped as assets
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use bevy_carp::level_file::LevelPack;
+
+// (settings key, menu action, asset path). packs show in the play menu once loaded
+pub const LEVEL_PACKS: [(&str, &str, &str); 1] =
+    [("Curated", "play curated", "levels/curated.pack.json")];
+
+pub struct LevelPackPlugin;
+
+impl Plugin for LevelPackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LevelPack>()
+            .init_asset_loader::<LevelPackLoader>()
+            .init_resource::<LevelPacks>()
+            .add_startup_system(load_packs);
+    }
+}
+
+#[derive(Default)]
+pub struct LevelPackLoader;
+
+impl AssetLoader for LevelPackLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let pack = LevelPack::from_json(bytes)?;
+            debug!("loaded pack {} ({} levels)", pack.title, pack.levels.len());
+            load_context.set_default_asset(LoadedAsset::new(pack));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pack.json"]
+    }
+}
+
+// handles in `LEVEL_PACKS` order
+#[derive(Default)]
+pub struct LevelPacks(pub Vec<Handle<LevelPack>>);
+
+fn load_packs(mut packs: ResMut<LevelPacks>, server: Res<AssetServer>) {
+    packs.0 = LEVEL_PACKS
+        .iter()
+        .map(|(_, _, path)| server.load(*path))
+        .collect();
+}
@@ -0,0 +1,361 @@
+// record the actions sent by a level's controllers and play them back through the same systems.
+// entities differ between runs, so actions are stored against the kind of controller that sent them.
+// the clock only runs while the level's controls are enabled, so time spent in menus isn't replayed.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use bevy::{
+    ecs::event::{Events, ManualEventReader},
+    prelude::*,
+    render::camera::Camera3d,
+};
+use bevy_carp::{cut::Cut, levels::LevelDef, model::Level};
+use bevy_pkv::PkvStore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    menus::{PopupMenu, PopupMenuEvent},
+    structs::{ActionEvent, ActionLabel, LevelSet, Position, SpawnLevelEvent},
+    Cursor, LevelControls, PlankComponent, Selected, SystemController,
+};
+
+pub const REPLAY_KEY: &str = "Replay";
+const SAVE_KEY: &str = "last replay";
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_FILE: &str = "last.replay.json";
+// camera housekeeping and menus aren't part of a solve
+const SKIPPED_LABELS: [&str; 2] = ["focus", "pause"];
+const MOVE_LABELS: [&str; 6] = ["left", "right", "up", "down", "forward", "backward"];
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Replay {
+            pending: replay_from_args(),
+            ..Default::default()
+        })
+        .add_system(replay_events)
+        .add_system_to_stage(CoreStage::PreUpdate, advance_clock)
+        .add_system_to_stage(CoreStage::PreUpdate, play_actions.after(advance_clock))
+        .add_system_to_stage(CoreStage::PostUpdate, record_actions);
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ReplaySender {
+    Cursor,
+    Camera,
+    System,
+    Cutter,
+    // the plank in hand
+    Plank,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayAction {
+    pub frame: u32,
+    // seconds since the level started
+    pub time: f32,
+    pub sender: ReplaySender,
+    pub label: String,
+    // the cursor cell, so grabs and cuts land in the same place if movement drifts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<[i32; 2]>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ReplayFile {
+    pub def: LevelDef,
+    pub actions: Vec<ReplayAction>,
+}
+
+struct Playback {
+    file: ReplayFile,
+    next: usize,
+    started: bool,
+}
+
+#[derive(Default)]
+pub struct Replay {
+    // the current level's actions so far
+    recording: Option<ReplayFile>,
+    playback: Option<Playback>,
+    // the last finished or saved recording
+    last: Option<ReplayFile>,
+    frame: u32,
+    time: f32,
+    // a replay to watch instead of the main menu at startup, from `--replay <path>`
+    pub pending: Option<ReplayFile>,
+}
+
+impl Replay {
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+}
+
+/// marks the level controllers during playback, the input layer leaves them alone
+#[derive(Component)]
+pub struct Replaying;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn replay_from_args() -> Option<ReplayFile> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay").skip(1);
+    let path = args.next()?;
+    match std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()))
+    {
+        Ok(file) => Some(file),
+        Err(e) => {
+            warn!("failed to load replay {}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn replay_from_args() -> Option<ReplayFile> {
+    None
+}
+
+#[cfg(all(not(target_arch = "wasm32"), not(test)))]
+pub fn save_path() -> PathBuf {
+    PathBuf::from(SAVE_FILE)
+}
+
+// tests keep out of the working directory
+#[cfg(test)]
+pub fn save_path() -> PathBuf {
+    crate::headless::scratch_dir().join(SAVE_FILE)
+}
+
+fn save_replay(file: &ReplayFile, settings: &mut PkvStore) {
+    settings.set(SAVE_KEY, file).unwrap();
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(e) = std::fs::write(save_path(), serde_json::to_string_pretty(file).unwrap()) {
+        warn!("failed to write {}: {}", save_path().display(), e);
+    }
+}
+
+fn advance_clock(mut replay: ResMut<Replay>, time: Res<Time>, controls: LevelControls) {
+    if controls.enabled() {
+        replay.frame += 1;
+        replay.time += time.delta_seconds();
+    }
+}
+
+fn replay_events(
+    mut actions: EventReader<ActionEvent>,
+    mut spawn_evs: ResMut<Events<SpawnLevelEvent>>,
+    mut spawn_reader: Local<ManualEventReader<SpawnLevelEvent>>,
+    mut replay: ResMut<Replay>,
+    mut set: ResMut<LevelSet>,
+    mut popup: EventWriter<PopupMenuEvent>,
+    mut settings: ResMut<PkvStore>,
+) {
+    for ev in spawn_reader.iter(&spawn_evs) {
+        replay.frame = 0;
+        replay.time = 0.0;
+        match replay.playback.as_mut() {
+            Some(playback) if !playback.started => playback.started = true,
+            _ => {
+                replay.playback = None;
+                replay.recording = Some(ReplayFile {
+                    def: ev.def.clone(),
+                    actions: Vec::new(),
+                });
+            }
+        }
+    }
+
+    for ev in actions.iter() {
+        match ev.label.0 {
+            "main menu" => {
+                replay.playback = None;
+                replay.recording = None;
+            }
+            "save replay" => {
+                let Some(file) = replay.recording.clone() else {
+                    continue;
+                };
+                save_replay(&file, &mut settings);
+                replay.last = Some(file);
+                popup.send(PopupMenuEvent {
+                    sender: ev.sender,
+                    menu: PopupMenu {
+                        heading: "Replay saved".into(),
+                        items: vec![("Ok".into(), ActionLabel("cancel"), true)],
+                        cancel_action: Some(ActionLabel("cancel")),
+                        ..Default::default()
+                    },
+                    sound: false,
+                });
+            }
+            "watch replay" => {
+                let file = replay
+                    .pending
+                    .take()
+                    .or_else(|| replay.last.clone())
+                    .or_else(|| settings.get::<ReplayFile>(SAVE_KEY).ok());
+                let Some(file) = file else {
+                    continue;
+                };
+                *set = LevelSet {
+                    levels: vec![file.def.clone()],
+                    current_level: 0,
+                    title: "Replay".into(),
+                    settings_key: REPLAY_KEY.into(),
+                    recipe: None,
+                };
+                spawn_evs.send(SpawnLevelEvent {
+                    def: file.def.clone(),
+                });
+                replay.recording = None;
+                replay.playback = Some(Playback {
+                    file,
+                    next: 0,
+                    started: false,
+                });
+            }
+            _ => (),
+        }
+    }
+}
+
+fn record_actions(
+    mut actions: EventReader<ActionEvent>,
+    mut replay: ResMut<Replay>,
+    level: Res<Level>,
+    mut settings: ResMut<PkvStore>,
+    cursor: Query<&Position, With<Cursor>>,
+    camera: Query<(), With<Camera3d>>,
+    system: Query<(), With<SystemController>>,
+    cutter: Query<(), With<Cut>>,
+    plank: Query<(), (With<PlankComponent>, With<Selected>)>,
+) {
+    let replay = &mut *replay;
+    let Some(recording) = replay.recording.as_mut() else {
+        return;
+    };
+
+    for ev in actions.iter() {
+        if SKIPPED_LABELS.contains(&ev.label.0) {
+            continue;
+        }
+
+        let mut at = None;
+        let sender = if let Ok(pos) = cursor.get(ev.sender) {
+            at = Some(pos.0.into());
+            ReplaySender::Cursor
+        } else if camera.get(ev.sender).is_ok() {
+            ReplaySender::Camera
+        } else if system.get(ev.sender).is_ok() {
+            ReplaySender::System
+        } else if cutter.get(ev.sender).is_ok() {
+            ReplaySender::Cutter
+        } else if plank.get(ev.sender).is_ok() {
+            ReplaySender::Plank
+        } else {
+            continue;
+        };
+
+        recording.actions.push(ReplayAction {
+            frame: replay.frame,
+            time: replay.time,
+            sender,
+            label: ev.label.0.to_string(),
+            at,
+        });
+    }
+
+    if level.is_won() {
+        debug!("recorded {} actions", recording.actions.len());
+        let file = replay.recording.take().unwrap();
+        save_replay(&file, &mut settings);
+        replay.last = Some(file);
+    }
+}
+
+fn play_actions(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut actions: EventWriter<ActionEvent>,
+    mut cursor: Query<(Entity, &mut Position), With<Cursor>>,
+    camera: Query<Entity, With<Camera3d>>,
+    system: Query<Entity, With<SystemController>>,
+    cutter: Query<Entity, With<Cut>>,
+    plank: Query<Entity, (With<PlankComponent>, With<Selected>)>,
+    replaying: Query<Entity, With<Replaying>>,
+) {
+    let replay = &mut *replay;
+    let Some(playback) = replay.playback.as_mut() else {
+        for ent in replaying.iter() {
+            commands.entity(ent).remove::<Replaying>();
+        }
+        return;
+    };
+    if !playback.started {
+        return;
+    }
+
+    let cursor_ent = cursor.get_single().ok().map(|(ent, _)| ent);
+    for ent in cursor_ent
+        .into_iter()
+        .chain(camera.iter())
+        .chain(system.iter())
+        .chain(cutter.iter())
+        .chain(plank.iter())
+    {
+        if replaying.get(ent).is_err() {
+            commands.entity(ent).insert(Replaying);
+        }
+    }
+
+    let mut to_send = Vec::new();
+    while let Some(action) = playback.file.actions.get(playback.next) {
+        if action.time > replay.time {
+            break;
+        }
+
+        let sender = match action.sender {
+            ReplaySender::Cursor => cursor_ent,
+            ReplaySender::Camera => camera.get_single().ok(),
+            ReplaySender::System => system.get_single().ok(),
+            ReplaySender::Cutter => cutter.get_single().ok(),
+            ReplaySender::Plank => plank.get_single().ok(),
+        };
+        let Some(sender) = sender else {
+            debug!("replay: no {:?} for {}", action.sender, action.label);
+            playback.next += 1;
+            continue;
+        };
+
+        if let (Some(at), Ok((_, mut pos))) = (action.at, cursor.get_mut(sender)) {
+            if !MOVE_LABELS.contains(&action.label.as_str()) && pos.0 != IVec2::from(at) {
+                debug!("replay: cursor drifted to {}, expected {:?}", pos.0, at);
+                pos.0 = IVec2::from(at);
+                // send it next frame, once the target has caught up with the cursor
+                break;
+            }
+        }
+
+        playback.next += 1;
+        to_send.push((sender, action.label.clone()));
+    }
+
+    if playback.next == playback.file.actions.len() {
+        debug!("replay finished");
+        replay.playback = None;
+    }
+
+    for (sender, label) in to_send {
+        actions.send(ActionEvent {
+            sender,
+            label: ActionLabel::intern(&label),
+            target: None,
+        });
+    }
+}
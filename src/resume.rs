@@ -0,0 +1,157 @@
+// keep the level in progress when pausing or quitting, so it can be continued from the main menu.
+// the whole undo history is kept, so undo still works after continuing.
+
+use bevy::{prelude::*, window::WindowCloseRequested};
+use bevy_carp::{
+    levels::{build_level, LevelDef, LevelSet, SetRecipe},
+    model::{Level, LevelBase, Plank, Position, PositionZ, UndoBuffer},
+    stats::LevelStats,
+};
+use bevy_pkv::PkvStore;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    editor::{Editor, EDITOR_KEY},
+    replay::{Replay, REPLAY_KEY},
+    structs::{ActionEvent, ChangeBackground},
+    Cursor, DonePlanks, PlankComponent, ResetEvent,
+};
+
+const SAVE_KEY: &str = "saved level";
+
+pub struct ResumePlugin;
+
+impl Plugin for ResumePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(save_level).add_system(continue_level);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedLevel {
+    levels: Vec<LevelDef>,
+    current_level: usize,
+    title: String,
+    settings_key: String,
+    #[serde(default)]
+    recipe: Option<SetRecipe>,
+    level: Level,
+    done_planks: Vec<(Plank, Position, Vec<IVec2>)>,
+    cursor: Position,
+    camera: (Position, PositionZ),
+    undo: UndoBuffer,
+    stats: LevelStats,
+}
+
+/// test plays and replays aren't worth continuing
+pub fn is_resumable(set: &LevelSet) -> bool {
+    !set.levels.is_empty() && set.settings_key != EDITOR_KEY && set.settings_key != REPLAY_KEY
+}
+
+pub fn has_saved_level(settings: &PkvStore) -> bool {
+    matches!(settings.get::<Option<SavedLevel>>(SAVE_KEY), Ok(Some(_)))
+}
+
+pub fn clear_saved_level(settings: &mut PkvStore) {
+    settings.set(SAVE_KEY, &None::<SavedLevel>).unwrap();
+}
+
+fn save_level(
+    mut actions: EventReader<ActionEvent>,
+    mut close: EventReader<WindowCloseRequested>,
+    mut settings: ResMut<PkvStore>,
+    set: Res<LevelSet>,
+    level: Res<Level>,
+    done_planks: Res<DonePlanks>,
+    undo: Res<UndoBuffer>,
+    stats: Res<LevelStats>,
+    editor: Res<Editor>,
+    replay: Res<Replay>,
+    planks: Query<(&Position, &PlankComponent)>,
+    cursor: Query<&Position, With<Cursor>>,
+    camera: Query<(&Position, &PositionZ), With<Camera>>,
+) {
+    let closing = close.iter().count() > 0;
+    let leaving = actions
+        .iter()
+        .any(|ev| matches!(ev.label.0, "pause" | "quit"));
+    if !closing && !leaving {
+        return;
+    }
+
+    if editor.active || replay.is_playing() || !is_resumable(&set) || level.is_won() {
+        return;
+    }
+    // only while a level is on screen
+    let (Ok(&cursor), Ok((&cam_pos, &cam_pos_z))) = (cursor.get_single(), camera.get_single())
+    else {
+        return;
+    };
+
+    // the level resource only holds plank positions from the last reset
+    let mut level = level.clone();
+    level.planks = planks
+        .iter()
+        .map(|(pos, plank)| (plank.0.clone(), *pos))
+        .collect();
+
+    let saved = SavedLevel {
+        levels: set.levels.clone(),
+        current_level: set.current_level,
+        title: set.title.clone(),
+        settings_key: set.settings_key.to_string(),
+        recipe: set.recipe.clone(),
+        level,
+        done_planks: done_planks.0.clone(),
+        cursor,
+        camera: (cam_pos, cam_pos_z),
+        undo: undo.clone(),
+        stats: *stats,
+    };
+    settings.set(SAVE_KEY, &Some(saved)).unwrap();
+    debug!("saved level {}", set.current_level);
+}
+
+fn continue_level(
+    mut actions: EventReader<ActionEvent>,
+    mut commands: Commands,
+    settings: Res<PkvStore>,
+    mut set: ResMut<LevelSet>,
+    mut def: ResMut<LevelDef>,
+    mut base: ResMut<LevelBase>,
+    mut level: ResMut<Level>,
+    mut done_planks: ResMut<DonePlanks>,
+    mut stats: ResMut<LevelStats>,
+    mut bg: EventWriter<ChangeBackground>,
+    mut reset: EventWriter<ResetEvent>,
+) {
+    for ev in actions.iter() {
+        if ev.label.0 != "continue" {
+            continue;
+        }
+        let Ok(Some(saved)) = settings.get::<Option<SavedLevel>>(SAVE_KEY) else {
+            continue;
+        };
+
+        *set = LevelSet {
+            settings_key: saved.settings_key.into(),
+            levels: saved.levels,
+            current_level: saved.current_level,
+            title: saved.title,
+            recipe: saved.recipe,
+        };
+        *def = set.levels[set.current_level].clone();
+        *base = LevelBase(build_level(&def));
+        *level = saved.level;
+        done_planks.0 = saved.done_planks;
+        *stats = saved.stats;
+        commands.insert_resource(saved.undo);
+
+        bg.send_default();
+        reset.send(ResetEvent {
+            cursor_pos: Some(saved.cursor),
+            camera_pos: Some(saved.camera),
+            ..Default::default()
+        });
+    }
+}
@@ -0,0 +1,192 @@
+// short text codes for generated levels and random sets, so a puzzle can be passed around.
+// a level is `L<holes>-<blocks>-<seed>` and a set `S<total>-<skip>-<count>-<seed>-<title>`, numbers in base 36.
+// a set from a later generator has `.<generator>` after its seed.
+
+use crate::levels::{LevelDef, SetRecipe, MAX_BLOCKS, MAX_HOLES, MAX_SET_TOTAL, SET_GENERATOR};
+
+#[derive(Clone)]
+pub enum ShareCode {
+    Level(LevelDef),
+    Set(SetRecipe),
+}
+
+const DIGITS: &[u8; 36] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+fn encode_number(mut n: u64) -> String {
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[(n % 36) as usize]);
+        n /= 36;
+        if n == 0 {
+            break;
+        }
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+fn decode_number(s: &str) -> Result<u64, String> {
+    u64::from_str_radix(s, 36).map_err(|_| format!("bad number '{}'", s))
+}
+
+impl ShareCode {
+    pub fn encode(&self) -> String {
+        match self {
+            ShareCode::Level(def) => format!(
+                "L{}-{}-{}",
+                encode_number(def.num_holes as u64),
+                encode_number(def.total_blocks as u64),
+                encode_number(def.seed)
+            ),
+            ShareCode::Set(recipe) => {
+                let mut seed = encode_number(recipe.seed);
+                if recipe.generator > 0 {
+                    seed = format!("{}.{}", seed, encode_number(recipe.generator as u64));
+                }
+                format!(
+                    "S{}-{}-{}-{}-{}",
+                    encode_number(recipe.total as u64),
+                    encode_number(recipe.skip as u64),
+                    encode_number(recipe.count as u64),
+                    seed,
+                    recipe.title
+                )
+            }
+        }
+    }
+
+    /// parse a code, checking a level can be generated and a set isn't empty
+    pub fn decode(code: &str) -> Result<Self, String> {
+        let code = code.trim();
+        let mut chars = code.chars();
+        let kind = chars.next().ok_or("empty code")?;
+        let rest = chars.as_str();
+
+        match kind.to_ascii_uppercase() {
+            'L' => {
+                let parts = rest
+                    .split('-')
+                    .map(decode_number)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let [num_holes, total_blocks, seed] = parts[..] else {
+                    return Err("a level code has 3 parts".into());
+                };
+                let def = LevelDef {
+                    num_holes: num_holes as usize,
+                    total_blocks: total_blocks as usize,
+                    seed,
+                    file: None,
+                };
+                if def.num_holes > MAX_HOLES || def.total_blocks > MAX_BLOCKS {
+                    return Err(format!(
+                        "levels are at most {} holes and {} blocks",
+                        MAX_HOLES, MAX_BLOCKS
+                    ));
+                }
+                def.check_generator()?;
+                Ok(ShareCode::Level(def))
+            }
+            'S' => {
+                let parts = rest.splitn(5, '-').collect::<Vec<_>>();
+                let [total, skip, count, seed, title] = parts[..] else {
+                    return Err("a set code has 5 parts".into());
+                };
+                let (seed, generator) = match seed.split_once('.') {
+                    Some((seed, generator)) => (seed, decode_number(generator)?),
+                    None => (seed, 0),
+                };
+                if generator > SET_GENERATOR as u64 {
+                    return Err("the set needs a newer version of the game".into());
+                }
+                let recipe = SetRecipe {
+                    total: decode_number(total)? as usize,
+                    skip: decode_number(skip)? as usize,
+                    count: decode_number(count)? as usize,
+                    seed: decode_number(seed)?,
+                    title: title.to_string(),
+                    generator: generator as u32,
+                };
+                if recipe.count == 0 || recipe.skip >= recipe.total {
+                    return Err("the set has no levels".into());
+                }
+                // a set generates all `total` defs on the spot, keep it to what the menus ask for
+                if recipe.total > MAX_SET_TOTAL {
+                    return Err(format!("sets are at most {} levels", MAX_SET_TOTAL));
+                }
+                Ok(ShareCode::Set(recipe))
+            }
+            _ => Err(format!("'{}' isn't a level code", code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_codes() {
+        let def = LevelDef {
+            num_holes: 5,
+            total_blocks: 23,
+            seed: 123456789,
+            file: None,
+        };
+        let code = ShareCode::Level(def).encode();
+        let Ok(ShareCode::Level(decoded)) = ShareCode::decode(&code.to_lowercase()) else {
+            panic!("{} didn't decode", code);
+        };
+        assert_eq!(
+            (decoded.num_holes, decoded.total_blocks, decoded.seed),
+            (5, 23, 123456789)
+        );
+
+        // bigger than the custom menu makes
+        let too_big = format!(
+            "L{}-{}-0",
+            encode_number(MAX_HOLES as u64 + 1),
+            encode_number(100)
+        );
+        assert!(ShareCode::decode(&too_big).is_err());
+        assert!(ShareCode::decode("L1-1-0-0").is_err());
+    }
+
+    #[test]
+    fn set_codes() {
+        let recipe = SetRecipe {
+            total: MAX_SET_TOTAL,
+            skip: 30,
+            count: 30,
+            seed: 15,
+            title: "Medium - Set".into(),
+            generator: 0,
+        };
+        let code = ShareCode::Set(recipe.clone()).encode();
+        // codes from before generators still read the same
+        assert_eq!(code, "S2I-U-U-F-Medium - Set");
+        let Ok(ShareCode::Set(decoded)) = ShareCode::decode(&code) else {
+            panic!("{} didn't decode", code);
+        };
+        assert_eq!(decoded, recipe);
+
+        let newer = SetRecipe {
+            generator: SET_GENERATOR,
+            ..recipe
+        };
+        let code = ShareCode::Set(newer.clone()).encode();
+        let Ok(ShareCode::Set(decoded)) = ShareCode::decode(&code) else {
+            panic!("{} didn't decode", code);
+        };
+        assert_eq!(decoded, newer);
+        let unknown = format!(
+            "S2I-U-U-F.{}-Later",
+            encode_number(SET_GENERATOR as u64 + 1)
+        );
+        assert!(ShareCode::decode(&unknown).is_err());
+
+        // bigger than the menus make
+        let too_big = format!("S{}-0-1-0-Big", encode_number(MAX_SET_TOTAL as u64 + 1));
+        assert!(ShareCode::decode(&too_big).is_err());
+        assert!(ShareCode::decode("S10-10-1-0-Empty").is_err());
+    }
+}
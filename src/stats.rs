@@ -0,0 +1,146 @@
+// how well a level was played, compared against the solver's cut count, and local leaderboards of results
+
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{levels::LevelDef, share::ShareCode};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub struct LevelStats {
+    // cuts the solver's solution takes
+    pub par: usize,
+    // finished cuts, including ones later undone
+    pub cuts: usize,
+    pub undos: usize,
+    // seconds spent playing, not counting menus
+    pub time: f32,
+}
+
+impl LevelStats {
+    pub fn new(par: usize) -> Self {
+        Self {
+            par,
+            ..Default::default()
+        }
+    }
+
+    /// 3 for par without undoing, 2 for at most two extra cuts, otherwise 1
+    pub fn stars(&self) -> usize {
+        let extra = self.cuts.saturating_sub(self.par);
+        match (extra, self.undos) {
+            (0, 0) => 3,
+            (0..=2, _) => 2,
+            _ => 1,
+        }
+    }
+
+    /// more stars, then fewer cuts, then faster
+    pub fn is_better_than(&self, other: &LevelStats) -> bool {
+        match self
+            .stars()
+            .cmp(&other.stars())
+            .then(other.cuts.cmp(&self.cuts))
+        {
+            Ordering::Equal => self.time < other.time,
+            ord => ord == Ordering::Greater,
+        }
+    }
+
+    /// settings key for a level's best result in a set
+    pub fn best_key(settings_key: &str, def: &LevelDef) -> String {
+        format!("{} best {}", settings_key, level_id(def))
+    }
+}
+
+// entries kept per level
+pub const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub stats: LevelStats,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    /// fewest cuts, then fastest, then fewest undos
+    fn compare(a: &LevelStats, b: &LevelStats) -> Ordering {
+        a.cuts
+            .cmp(&b.cuts)
+            .then(a.time.total_cmp(&b.time))
+            .then(a.undos.cmp(&b.undos))
+    }
+
+    /// add a result, returning its rank if it made the table. ties go below existing entries
+    pub fn insert(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| Self::compare(&entry.stats, &other.stats) == Ordering::Less)
+            .unwrap_or(self.entries.len());
+        if rank >= LEADERBOARD_SIZE {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(LEADERBOARD_SIZE);
+        Some(rank)
+    }
+
+    /// settings key for a level's table. generated levels are the same puzzle wherever they're played, so
+    /// they share a table, hand authored levels are only unique within their set
+    pub fn key(settings_key: &str, def: &LevelDef) -> String {
+        match def.file {
+            None => format!("leaderboard {}", level_id(def)),
+            Some(_) => format!("leaderboard {} {}", settings_key, level_id(def)),
+        }
+    }
+}
+
+// generated levels by their share code, which covers holes, blocks and seed. hand authored levels by their
+// place in the set
+fn level_id(def: &LevelDef) -> String {
+    match def.file {
+        None => ShareCode::Level(def.clone()).encode(),
+        Some(_) => def.seed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_tell_levels_apart() {
+        let def = |num_holes, total_blocks| LevelDef {
+            num_holes,
+            total_blocks,
+            seed: 7,
+            file: None,
+        };
+
+        // the same seed makes a different puzzle at a different size
+        assert_ne!(
+            Leaderboard::key("Easy", &def(3, 12)),
+            Leaderboard::key("Easy", &def(4, 12))
+        );
+        assert_ne!(
+            Leaderboard::key("Easy", &def(3, 12)),
+            Leaderboard::key("Easy", &def(3, 13))
+        );
+        assert_ne!(
+            LevelStats::best_key("Easy", &def(3, 12)),
+            LevelStats::best_key("Easy", &def(4, 12))
+        );
+
+        // a generated level has one table wherever it's played
+        assert_eq!(
+            Leaderboard::key("Easy", &def(3, 12)),
+            Leaderboard::key("Shared", &def(3, 12))
+        );
+    }
+}
@@ -1,8 +1,30 @@
+use std::sync::Mutex;
+
 use bevy::prelude::*;
 
+pub use bevy_carp::{
+    levels::{LevelDef, LevelSet},
+    model::{Position, PositionZ},
+};
+
 #[derive(Clone, Copy, Deref, PartialEq, Eq, Debug)]
 pub struct ActionLabel(pub &'static str);
 
+// labels read back at runtime, each distinct one is leaked once
+static INTERNED: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+impl ActionLabel {
+    pub fn intern(label: &str) -> Self {
+        let mut interned = INTERNED.lock().unwrap();
+        if let Some(existing) = interned.iter().find(|l| **l == label) {
+            return ActionLabel(existing);
+        }
+        let leaked: &'static str = Box::leak(label.to_string().into_boxed_str());
+        interned.push(leaked);
+        ActionLabel(leaked)
+    }
+}
+
 #[derive(Debug)]
 pub struct ActionEvent {
     pub sender: Entity,
@@ -10,27 +32,6 @@ pub struct ActionEvent {
     pub target: Option<Entity>,
 }
 
-#[derive(Component, Clone, Copy, Default, PartialEq, Eq)]
-pub struct Position(pub IVec2);
-
-#[derive(Component, Clone, Copy, Default)]
-pub struct PositionZ(pub i32);
-
-#[derive(Clone, Default)]
-pub struct LevelDef {
-    pub num_holes: usize,
-    pub total_blocks: usize,
-    pub seed: u64,
-}
-
-#[derive(Default, Clone)]
-pub struct LevelSet {
-    pub levels: [LevelDef; 30],
-    pub current_level: usize,
-    pub title: String,
-    pub settings_key: &'static str,
-}
-
 pub struct SpawnLevelEvent {
     pub def: LevelDef,
 }
@@ -0,0 +1,302 @@
+// play with a touchscreen. one finger works like the mouse: tap to grab and drop, drag off a plank to cut it,
+// and drag on empty space to pan. two fingers pinch to zoom and twist to turn. the first finger also acts as
+// egui's pointer so menus can be tapped, and undo, redo and pause get buttons on screen. on the web the
+// touches come from `web_touch`
+
+use std::f32::consts::{FRAC_PI_4, PI, TAU};
+
+use bevy::{
+    input::touch::Touches, prelude::*, render::camera::Camera3d, utils::HashMap, window::WindowId,
+};
+use bevy_egui::{egui, EguiContext, EguiInput, EguiSettings, EguiSystem};
+
+use crate::{
+    input::{read_mouse, ActionType, Controller, DisplayMode, MouseInputs},
+    mouse::{Gesture, PointerPlay},
+    structs::{ActionEvent, Position},
+    Cursor, PositionOffset, SystemController,
+};
+
+// how far a finger moves, in pixels, before a press on empty space is a pan rather than a tap
+const TAP_SLOP: f32 = 10.0;
+// how much two fingers spread or close to zoom a step
+const PINCH_STEP: f32 = 1.25;
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TouchUsed>()
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                touch_to_egui
+                    .after(EguiSystem::ProcessInput)
+                    .before(EguiSystem::BeginFrame),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, touch_play.after(read_mouse))
+            .add_system(touch_buttons);
+
+        #[cfg(target_arch = "wasm32")]
+        app.add_system_to_stage(
+            CoreStage::PreUpdate,
+            crate::web_touch::forward_touches.before(bevy::input::InputSystem),
+        );
+    }
+}
+
+/// a finger has touched the screen, so show the on screen buttons
+#[derive(Default)]
+pub struct TouchUsed(pub bool);
+
+// the first finger down is egui's pointer until it lifts
+fn touch_to_egui(
+    touches: Res<Touches>,
+    windows: Res<Windows>,
+    settings: Res<EguiSettings>,
+    mut inputs: ResMut<HashMap<WindowId, EguiInput>>,
+    mut pointer: Local<Option<u64>>,
+) {
+    let (Some(window), Some(input)) = (windows.get_primary(), inputs.get_mut(&WindowId::primary()))
+    else {
+        return;
+    };
+    let scale = settings.scale_factor as f32;
+    let to_egui = |pos: Vec2| egui::pos2(pos.x / scale, (window.height() - pos.y) / scale);
+    let events = &mut input.raw_input.events;
+
+    if pointer.is_none() {
+        if let Some(touch) = touches.iter_just_pressed().next() {
+            let pos = to_egui(touch.position());
+            events.push(egui::Event::PointerMoved(pos));
+            events.push(egui::Event::PointerButton {
+                pos,
+                button: egui::PointerButton::Primary,
+                pressed: true,
+                modifiers: Default::default(),
+            });
+            *pointer = Some(touch.id());
+        }
+    }
+
+    let Some(id) = *pointer else {
+        return;
+    };
+    if let Some(touch) = touches.get_pressed(id) {
+        if touch.delta() != Vec2::ZERO {
+            events.push(egui::Event::PointerMoved(to_egui(touch.position())));
+        }
+    } else {
+        let lifted = touches.get_released(id).map(|touch| touch.position());
+        if let Some(pos) = lifted {
+            events.push(egui::Event::PointerButton {
+                pos: to_egui(pos),
+                button: egui::PointerButton::Primary,
+                pressed: false,
+                modifiers: Default::default(),
+            });
+        }
+        events.push(egui::Event::PointerGone);
+        *pointer = None;
+    }
+}
+
+#[derive(Default)]
+enum TouchMode {
+    #[default]
+    None,
+    // started on a menu or panel, ignored until lifted
+    Ui,
+    // one finger working the cursor like the mouse
+    Point(u64),
+    // one finger dragging the view, with the board distance not panned yet and the pixels moved
+    Pan {
+        id: u64,
+        pan: Vec2,
+        travel: f32,
+    },
+    // two fingers, with their spread and angle when last zoomed or turned
+    Pinch {
+        spread: f32,
+        angle: f32,
+    },
+}
+
+#[derive(Default)]
+struct TouchState {
+    mode: TouchMode,
+    gesture: Gesture,
+}
+
+fn touch_play(
+    touches: Res<Touches>,
+    mouse: Res<MouseInputs>,
+    mut used: ResMut<TouchUsed>,
+    camera: Query<(&Transform, &Position, &PositionOffset), With<Camera3d>>,
+    cursor: Query<(&Transform, &Position), With<Cursor>>,
+    mut play: PointerPlay,
+    mut state: Local<TouchState>,
+) {
+    let TouchState { mode, gesture } = &mut *state;
+    let mut fingers = touches.iter().collect::<Vec<_>>();
+    fingers.sort_by_key(|touch| touch.id());
+    if !fingers.is_empty() && !used.0 {
+        used.0 = true;
+    }
+
+    match fingers[..] {
+        [] => {
+            if touches.iter_just_cancelled().next().is_some() {
+                gesture.cancel(&mut play);
+            }
+            for touch in touches.iter_just_released() {
+                match mode {
+                    TouchMode::Point(id) if *id == touch.id() => gesture.up(),
+                    // a tap on empty space moves the cursor there
+                    TouchMode::Pan { id, travel, .. }
+                        if *id == touch.id() && *travel < TAP_SLOP =>
+                    {
+                        if let Some(point) = play.board(touch.position()) {
+                            gesture.move_to(point);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            *mode = TouchMode::None;
+        }
+        [finger] => {
+            let point = play.board(finger.position());
+            match mode {
+                TouchMode::None => {
+                    if mouse.over_ui {
+                        *mode = TouchMode::Ui;
+                    } else if let Some(point) = point {
+                        if play.holding() || play.plank_at(point.floor().as_ivec2()) {
+                            gesture.down(&play, point);
+                            *mode = TouchMode::Point(finger.id());
+                        } else {
+                            *mode = TouchMode::Pan {
+                                id: finger.id(),
+                                pan: Vec2::ZERO,
+                                travel: 0.0,
+                            };
+                        }
+                    }
+                }
+                TouchMode::Point(id) if *id == finger.id() => {
+                    if let Some(point) = point {
+                        gesture.move_to(point);
+                    }
+                }
+                TouchMode::Pan { id, pan, travel } if *id == finger.id() => {
+                    *travel += finger.delta().length();
+                    if let (Some(from), Some(to)) = (play.board(finger.previous_position()), point)
+                    {
+                        *pan += from - to;
+                    }
+
+                    // the cursor goes with the view so the camera doesn't pull back to it. step once both
+                    // have caught up with the last step, as a held key would
+                    let caught_up = camera.get_single().is_ok_and(|(trans, pos, offset)| {
+                        trans.translation.truncate() == pos.0.as_vec2() + offset.0
+                    }) && cursor
+                        .get_single()
+                        .is_ok_and(|(trans, pos)| trans.translation.truncate() == pos.0.as_vec2());
+                    if caught_up {
+                        let steps = [
+                            (pan.x, Vec2::X, ActionType::PanRight, ActionType::MoveRight),
+                            (-pan.x, -Vec2::X, ActionType::PanLeft, ActionType::MoveLeft),
+                            (pan.y, Vec2::Y, ActionType::PanUp, ActionType::MoveUp),
+                            (-pan.y, -Vec2::Y, ActionType::PanDown, ActionType::MoveDown),
+                        ];
+                        for (distance, dir, pan_type, move_type) in steps {
+                            if distance >= 1.0 {
+                                play.press(pan_type);
+                                play.press(move_type);
+                                *pan -= dir;
+                            }
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+        [a, b, ..] => {
+            let between = b.position() - a.position();
+            let spread = between.length();
+            let angle = between.y.atan2(between.x);
+            match mode {
+                TouchMode::Pinch {
+                    spread: last_spread,
+                    angle: last_angle,
+                } => {
+                    if spread > *last_spread * PINCH_STEP {
+                        play.press(ActionType::ZoomIn);
+                        *last_spread = spread;
+                    } else if spread < *last_spread / PINCH_STEP {
+                        play.press(ActionType::ZoomOut);
+                        *last_spread = spread;
+                    }
+
+                    let turn = (angle - *last_angle + PI).rem_euclid(TAU) - PI;
+                    if turn > FRAC_PI_4 {
+                        play.press(ActionType::TurnLeft);
+                        *last_angle = angle;
+                    } else if turn < -FRAC_PI_4 {
+                        play.press(ActionType::TurnRight);
+                        *last_angle = angle;
+                    }
+                }
+                // a second finger ends whatever the first was doing
+                _ => {
+                    gesture.cancel(&mut play);
+                    *mode = TouchMode::Pinch { spread, angle };
+                }
+            }
+        }
+    }
+
+    let held = matches!(mode, TouchMode::Point(_));
+    gesture.update(&mut play, held);
+}
+
+// buttons for the level's undo, redo and pause, which have no gesture
+fn touch_buttons(
+    mut egui_context: ResMut<EguiContext>,
+    used: Res<TouchUsed>,
+    system: Query<(Entity, &Controller), With<SystemController>>,
+    mut actions: EventWriter<ActionEvent>,
+) {
+    let Ok((ent, controller)) = system.get_single() else {
+        return;
+    };
+    if !used.0 || !controller.enabled {
+        return;
+    }
+
+    egui::Area::new("touch buttons")
+        .anchor(egui::Align2::LEFT_BOTTOM, (5.0, -5.0))
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for ty in [
+                    ActionType::ThirdAction,
+                    ActionType::FourthAction,
+                    ActionType::Menu,
+                ] {
+                    let Some((_, action)) = controller.actions.iter().find(|(t, _)| *t == ty)
+                    else {
+                        continue;
+                    };
+                    let active = !matches!(action.display, DisplayMode::Inactive);
+                    let button = egui::Button::new(egui::RichText::new(action.label.0).size(30.0));
+                    if ui.add_enabled(active, button).clicked() {
+                        actions.send(ActionEvent {
+                            sender: ent,
+                            label: action.label,
+                            target: None,
+                        });
+                    }
+                }
+            });
+        });
+}
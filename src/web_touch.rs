@@ -0,0 +1,75 @@
+// browser touches. winit 0.26 turns them into mouse events on the web and never sends bevy a touch, so there'd
+// be no pinch, twist, pan or on screen buttons. listen on the canvas instead and pass them on as bevy's own
+// touch events. not passive, so the browser doesn't scroll or zoom the page under the game
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    input::touch::{TouchInput, TouchPhase},
+    prelude::*,
+};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{AddEventListenerOptions, HtmlCanvasElement, TouchEvent};
+
+type Queue = Arc<Mutex<Vec<TouchInput>>>;
+
+/// send the touches the canvas got since last frame, listening once the canvas is there
+pub fn forward_touches(mut queue: Local<Option<Queue>>, mut touches: EventWriter<TouchInput>) {
+    if queue.is_none() {
+        *queue = listen();
+    }
+    if let Some(queue) = queue.as_ref() {
+        touches.send_batch(queue.lock().unwrap().drain(..));
+    }
+}
+
+fn listen() -> Option<Queue> {
+    let canvas = web_sys::window()?
+        .document()?
+        .query_selector("canvas")
+        .ok()??
+        .dyn_into::<HtmlCanvasElement>()
+        .ok()?;
+    let queue = Queue::default();
+
+    let mut options = AddEventListenerOptions::new();
+    options.passive(false);
+    for (name, phase) in [
+        ("touchstart", TouchPhase::Started),
+        ("touchmove", TouchPhase::Moved),
+        ("touchend", TouchPhase::Ended),
+        ("touchcancel", TouchPhase::Cancelled),
+    ] {
+        let queue = queue.clone();
+        let target = canvas.clone();
+        let listener = Closure::wrap(Box::new(move |ev: TouchEvent| {
+            ev.prevent_default();
+            // logical pixels from the bottom left, like the window's cursor
+            let rect = target.get_bounding_client_rect();
+            let changed = ev.changed_touches();
+            let mut queue = queue.lock().unwrap();
+            for touch in (0..changed.length()).filter_map(|i| changed.get(i)) {
+                queue.push(TouchInput {
+                    phase,
+                    position: Vec2::new(
+                        (touch.client_x() as f64 - rect.left()) as f32,
+                        (rect.bottom() - touch.client_y() as f64) as f32,
+                    ),
+                    force: None,
+                    id: touch.identifier() as u64,
+                });
+            }
+        }) as Box<dyn FnMut(TouchEvent)>);
+        canvas
+            .add_event_listener_with_callback_and_add_event_listener_options(
+                name,
+                listener.as_ref().unchecked_ref(),
+                &options,
+            )
+            .ok()?;
+        // the canvas lasts as long as the page
+        listener.forget();
+    }
+
+    Some(queue)
+}
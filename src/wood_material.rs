@@ -1,24 +1,54 @@
+// planks and holes are drawn as instances of one quad. each piece's colours, turns and texture offset go in a
+// shared instance buffer and its cells in a shared atlas texture, so pieces don't each need an image, material
+// and draw call. pieces at the same height are drawn together, keeping the layering with everything else.
+
 use bevy::{
+    core::{Pod, Zeroable},
+    core_pipeline::Transparent3d,
+    ecs::system::{lifetimeless::*, SystemParamItem},
+    pbr::{MeshPipeline, MeshPipelineKey, SetMeshViewBindGroup},
     prelude::*,
-    reflect::TypeUuid,
-    render::render_resource::{std140::AsStd140, *},
+    render::{
+        mesh::{MeshVertexBufferLayout, PrimitiveTopology},
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, EntityRenderCommand, RenderCommandResult, RenderPhase,
+            SetItemPipeline, TrackedRenderPass,
+        },
+        render_resource::*,
+        renderer::{RenderDevice, RenderQueue},
+        view::{ExtractedView, Msaa},
+        RenderApp, RenderStage,
+    },
 };
+use bevy_carp::model::CoordSet;
 
-use crate::shader::{SimpleTextureMaterial, SimpleTextureSpec};
+use crate::bl_quad::BLQuad;
 
-pub struct WoodMaterialPlugin;
+// cells across the atlas. pieces are packed in rows below each other
+const ATLAS_WIDTH: u32 = 256;
 
-pub type WoodMaterial = SimpleTextureMaterial<WoodMaterialSpec>;
+pub struct WoodMaterialPlugin;
 
 impl Plugin for WoodMaterialPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(MaterialPlugin::<WoodMaterial>::default());
+        app.init_resource::<WoodAtlas>()
+            .add_system_to_stage(CoreStage::PostUpdate, pack_atlas);
+
+        app.sub_app_mut(RenderApp)
+            .add_render_command::<Transparent3d, DrawWood>()
+            .init_resource::<WoodPipeline>()
+            .init_resource::<SpecializedRenderPipelines<WoodPipeline>>()
+            .init_resource::<WoodInstances>()
+            .add_system_to_stage(RenderStage::Extract, extract_wood)
+            .add_system_to_stage(RenderStage::Prepare, prepare_wood)
+            .add_system_to_stage(RenderStage::Queue, queue_wood);
     }
 }
 
-#[derive(Clone, TypeUuid)]
-#[uuid = "8f83afc2-8543-40d9-b8ec-fbdb11051ebf"]
-pub struct WoodMaterialSpec {
+/// how a plank or hole looks. put on the entity to draw, the quad is `size` cells from its transform
+#[derive(Component, Clone)]
+pub struct WoodMaterial {
     pub primary_color: Color,
     pub secondary_color: Color,
     pub hilight_color: Color,
@@ -26,52 +56,342 @@ pub struct WoodMaterialSpec {
     pub size: UVec2,
     pub turns: usize,
     pub is_plank: bool,
-    pub base_color_texture: Handle<Image>,
+    // which cells are wood, or hole for holes
+    pub cells: CoordSet,
 }
 
-// 0.8 #[derive(ShaderType)]
-#[derive(AsStd140)]
-pub struct WoodMaterialUniformData {
-    pub primary_color: Vec4,
-    pub secondary_color: Vec4,
-    pub hilight_color: Vec4,
-    pub texture_offset: IVec2,
-    size: UVec2,
-    turns: u32,
-    is_plank: u32,
+// where each piece's cells are in the atlas image
+struct WoodAtlas {
+    image: Handle<Image>,
+    offsets: bevy::utils::HashMap<Entity, UVec2>,
+}
+
+impl FromWorld for WoodAtlas {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Self {
+            image: images.add(atlas_image(UVec2::ONE, vec![0])),
+            offsets: Default::default(),
+        }
+    }
+}
+
+fn atlas_image(size: UVec2, data: Vec<u8>) -> Image {
+    Image::new(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::R8Uint,
+    )
 }
 
-impl SimpleTextureSpec for WoodMaterialSpec {
-    type Param = ();
-    type Uniform = WoodMaterialUniformData;
+// lay the pieces out again when any come or go. the image is replaced in place, so there's only ever one
+fn pack_atlas(
+    mut atlas: ResMut<WoodAtlas>,
+    mut images: ResMut<Assets<Image>>,
+    woods: Query<(Entity, &WoodMaterial)>,
+    added: Query<(), Added<WoodMaterial>>,
+    removed: RemovedComponents<WoodMaterial>,
+) {
+    if added.is_empty() && removed.iter().next().is_none() {
+        return;
+    }
 
-    fn alpha_mode() -> AlphaMode {
-        AlphaMode::Blend
+    let width = woods
+        .iter()
+        .map(|(_, wood)| wood.size.x)
+        .fold(ATLAS_WIDTH, u32::max);
+    let mut offsets = bevy::utils::HashMap::default();
+    let mut cursor = UVec2::ZERO;
+    let mut row_height = 0;
+    for (ent, wood) in woods.iter() {
+        if cursor.x + wood.size.x > width {
+            cursor = UVec2::new(0, cursor.y + row_height);
+            row_height = 0;
+        }
+        offsets.insert(ent, cursor);
+        cursor.x += wood.size.x;
+        row_height = row_height.max(wood.size.y);
     }
-    fn texture_handle(&self) -> &Handle<Image> {
-        &self.base_color_texture
+    let size = UVec2::new(width, (cursor.y + row_height).max(1));
+
+    let mut data = vec![0; (size.x * size.y) as usize];
+    for (ent, wood) in woods.iter() {
+        let offset = offsets[&ent].as_ivec2();
+        for cell in wood.cells.coords.iter() {
+            if cell.cmpge(IVec2::ZERO).all() && cell.cmplt(wood.size.as_ivec2()).all() {
+                let at = offset + cell;
+                data[(at.x + at.y * size.x as i32) as usize] = 1;
+            }
+        }
+    }
+
+    images.set_untracked(atlas.image.clone_weak(), atlas_image(size, data));
+    atlas.offsets = offsets;
+}
+
+// the per piece data, in the order of the shader's instance attributes
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct WoodInstance {
+    model: [[f32; 4]; 4],
+    primary_color: [f32; 4],
+    secondary_color: [f32; 4],
+    hilight_color: [f32; 4],
+    texture_offset: [i32; 2],
+    size: [u32; 2],
+    atlas_offset: [u32; 2],
+    turns_is_plank: [u32; 2],
+}
+
+// all fields are plain numbers with no padding
+unsafe impl Zeroable for WoodInstance {}
+unsafe impl Pod for WoodInstance {}
+
+struct ExtractedWood {
+    atlas: Handle<Image>,
+    // sorted by height
+    instances: Vec<WoodInstance>,
+}
+
+fn extract_wood(
+    mut commands: Commands,
+    atlas: Res<WoodAtlas>,
+    woods: Query<(Entity, &WoodMaterial, &GlobalTransform, &Visibility)>,
+) {
+    let mut instances = woods
+        .iter()
+        .filter(|(.., visibility)| visibility.is_visible)
+        .filter_map(|(ent, wood, transform, _)| {
+            let atlas_offset = atlas.offsets.get(&ent)?;
+            Some(WoodInstance {
+                model: transform.compute_matrix().to_cols_array_2d(),
+                primary_color: wood.primary_color.as_linear_rgba_f32(),
+                secondary_color: wood.secondary_color.as_linear_rgba_f32(),
+                hilight_color: wood.hilight_color.as_linear_rgba_f32(),
+                texture_offset: wood.texture_offset.to_array(),
+                size: wood.size.to_array(),
+                atlas_offset: atlas_offset.to_array(),
+                turns_is_plank: [wood.turns as u32, wood.is_plank as u32],
+            })
+        })
+        .collect::<Vec<_>>();
+    instances.sort_by(|a, b| a.model[3][2].total_cmp(&b.model[3][2]));
+
+    commands.insert_resource(ExtractedWood {
+        atlas: atlas.image.clone_weak(),
+        instances,
+    });
+}
+
+struct WoodInstances(BufferVec<WoodInstance>);
+
+impl Default for WoodInstances {
+    fn default() -> Self {
+        Self(BufferVec::new(BufferUsages::VERTEX))
     }
-    fn sample_type() -> TextureSampleType {
-        TextureSampleType::Uint
+}
+
+fn prepare_wood(
+    extracted: Res<ExtractedWood>,
+    mut instances: ResMut<WoodInstances>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    instances.0.clear();
+    for instance in extracted.instances.iter() {
+        instances.0.push(*instance);
     }
-    fn fragment_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
+    instances.0.write_buffer(&render_device, &render_queue);
+}
+
+struct WoodPipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+    atlas_layout: BindGroupLayout,
+    // the mesh pipeline is specialized on a mesh, give it a quad's
+    quad_layout: MeshVertexBufferLayout,
+}
+
+impl FromWorld for WoodPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
         asset_server.watch_for_changes().unwrap();
-        Some(asset_server.load("shaders/wood.wgsl"))
+        let shader = asset_server.load("shaders/wood.wgsl");
+
+        let atlas_layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    entries: &[BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            multisampled: false,
+                            sample_type: TextureSampleType::Uint,
+                            view_dimension: TextureViewDimension::D2,
+                        },
+                        count: None,
+                    }],
+                    label: Some("wood_atlas_layout"),
+                });
+
+        Self {
+            shader,
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            atlas_layout,
+            quad_layout: Mesh::from(BLQuad::new(Vec2::ONE, Vec2::ZERO))
+                .get_mesh_vertex_buffer_layout(),
+        }
     }
+}
 
-    fn vertex_shader(asset_server: &AssetServer) -> Option<Handle<Shader>> {
-        Some(asset_server.load("shaders/wood.wgsl"))
+impl SpecializedRenderPipeline for WoodPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut descriptor = self
+            .mesh_pipeline
+            .specialize(key, &self.quad_layout)
+            .unwrap();
+
+        // the quad's corners come from the vertex index, so the only buffer is the instances
+        let formats = [
+            VertexFormat::Float32x4,
+            VertexFormat::Float32x4,
+            VertexFormat::Float32x4,
+            VertexFormat::Float32x4,
+            VertexFormat::Float32x4,
+            VertexFormat::Float32x4,
+            VertexFormat::Float32x4,
+            VertexFormat::Sint32x2,
+            VertexFormat::Uint32x2,
+            VertexFormat::Uint32x2,
+            VertexFormat::Uint32x2,
+        ];
+        descriptor.vertex.buffers = vec![VertexBufferLayout::from_vertex_formats(
+            VertexStepMode::Instance,
+            formats,
+        )];
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor.fragment.as_mut().unwrap().shader = self.shader.clone();
+        descriptor.layout = Some(vec![
+            self.mesh_pipeline.view_layout.clone(),
+            self.atlas_layout.clone(),
+        ]);
+        descriptor.primitive.cull_mode = None;
+        descriptor.label = Some("wood_pipeline".into());
+        descriptor
     }
+}
 
-    fn prepare_uniform_data(&self, _: &mut Self::Param) -> Option<Self::Uniform> {
-        Some(WoodMaterialUniformData {
-            primary_color: self.primary_color.as_linear_rgba_f32().into(),
-            secondary_color: self.secondary_color.as_linear_rgba_f32().into(),
-            texture_offset: self.texture_offset,
-            turns: self.turns as u32,
-            hilight_color: self.hilight_color.as_linear_rgba_f32().into(),
-            size: self.size,
-            is_plank: self.is_plank as u32,
-        })
+struct WoodBindGroup(BindGroup);
+
+// a run of instances at the same height
+#[derive(Component)]
+struct WoodBatch(std::ops::Range<u32>);
+
+fn queue_wood(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    wood_pipeline: Res<WoodPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<WoodPipeline>>,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    msaa: Res<Msaa>,
+    extracted: Res<ExtractedWood>,
+    gpu_images: Res<RenderAssets<Image>>,
+    render_device: Res<RenderDevice>,
+    mut views: Query<(&ExtractedView, &mut RenderPhase<Transparent3d>)>,
+) {
+    let Some(atlas) = gpu_images.get(&extracted.atlas) else {
+        return;
+    };
+    commands.insert_resource(WoodBindGroup(render_device.create_bind_group(
+        &BindGroupDescriptor {
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&atlas.texture_view),
+            }],
+            label: Some("wood_atlas_bind_group"),
+            layout: &wood_pipeline.atlas_layout,
+        },
+    )));
+
+    let draw_wood = draw_functions.read().get_id::<DrawWood>().unwrap();
+    let key = MeshPipelineKey::from_msaa_samples(msaa.samples)
+        | MeshPipelineKey::from_primitive_topology(PrimitiveTopology::TriangleList)
+        | MeshPipelineKey::TRANSPARENT_MAIN_PASS;
+    let pipeline = pipelines.specialize(&mut pipeline_cache, &wood_pipeline, key);
+
+    let mut batches = Vec::new();
+    let mut start = 0;
+    for (i, instance) in extracted.instances.iter().enumerate() {
+        let next = extracted.instances.get(i + 1);
+        if next.is_some_and(|next| next.model[3][2] == instance.model[3][2]) {
+            continue;
+        }
+        let entity = commands.spawn().insert(WoodBatch(start..i as u32 + 1)).id();
+        batches.push((entity, Vec4::from(instance.model[3])));
+        start = i as u32 + 1;
+    }
+
+    for (view, mut transparent_phase) in views.iter_mut() {
+        let view_row_2 = view.transform.compute_matrix().row(2);
+        for (entity, translation) in batches.iter() {
+            transparent_phase.add(Transparent3d {
+                entity: *entity,
+                pipeline,
+                draw_function: draw_wood,
+                distance: view_row_2.dot(*translation),
+            });
+        }
+    }
+}
+
+type DrawWood = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetWoodBindGroup<1>,
+    DrawWoodBatch,
+);
+
+struct SetWoodBindGroup<const I: usize>;
+
+impl<const I: usize> EntityRenderCommand for SetWoodBindGroup<I> {
+    type Param = SRes<WoodBindGroup>;
+
+    fn render<'w>(
+        _view: Entity,
+        _item: Entity,
+        bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        pass.set_bind_group(I, &bind_group.into_inner().0, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawWoodBatch;
+
+impl EntityRenderCommand for DrawWoodBatch {
+    type Param = (SRes<WoodInstances>, SQuery<Read<WoodBatch>>);
+
+    fn render<'w>(
+        _view: Entity,
+        item: Entity,
+        (instances, batches): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let (Some(buffer), Ok(batch)) = (instances.into_inner().0.buffer(), batches.get(item))
+        else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_vertex_buffer(0, buffer.slice(..));
+        pass.draw(0..6, batch.0.clone());
+        RenderCommandResult::Success
     }
 }